@@ -0,0 +1,213 @@
+use std::{fmt::Debug, time::Duration};
+
+use crate::{
+    edge::{Cost, Edge},
+    matrix::DistanceMatrix,
+    pipeline::PipelineConfig,
+    tour::Tour,
+    tsp_ext::{candidates::Candidates, local_search::Strategy, solver::Problem},
+};
+
+// Pipelines compared if nothing else is given
+pub const PIPELINES: [&str; 5] = [
+    "nn",
+    "nn -> two_opt",
+    "nn -> or_opt -> two_opt",
+    "nn -> three_opt",
+    "nn -> three_opt_sm",
+];
+
+// Number of seeds every pipeline runs with if nothing else is given
+pub const RUNS: u64 = 5;
+
+// NOTE: Results of one pipeline over every seed it ran with.
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub pipeline: String,
+    costs: Vec<Cost>,
+    elapsed: Vec<Duration>,
+}
+
+impl Row {
+    pub fn mean(&self) -> Cost {
+        self.costs.iter().sum::<Cost>() / self.costs.len() as Cost
+    }
+
+    pub fn min(&self) -> Cost {
+        self.costs.iter().copied().fold(Cost::INFINITY, Cost::min)
+    }
+
+    pub fn max(&self) -> Cost {
+        self.costs
+            .iter()
+            .copied()
+            .fold(Cost::NEG_INFINITY, Cost::max)
+    }
+
+    pub fn mean_elapsed(&self) -> Duration {
+        self.elapsed.iter().sum::<Duration>() / self.elapsed.len() as u32
+    }
+}
+
+// Every pipeline run on the same nodes once per seed, along with the best tour any of them found.
+pub struct Comparison {
+    pub rows: Vec<Row>,
+    pub best: Tour,
+}
+
+pub fn compare<E: Edge + Clone + Debug>(
+    nodes: &[E],
+    matrix: &DistanceMatrix,
+    candidates: &Candidates,
+    strategy: Strategy,
+    pipelines: &[PipelineConfig],
+    seeds: &[u64],
+) -> Comparison {
+    let mut rows = vec![];
+    let mut best: Option<(Cost, Tour)> = None;
+
+    for config in pipelines {
+        let pipeline = config.build();
+        let mut row = Row {
+            pipeline: config.to_string(),
+            costs: vec![],
+            elapsed: vec![],
+        };
+
+        for seed in seeds {
+            let problem = Problem::new(nodes, matrix)
+                .with_candidates(candidates)
+                .with_strategy(strategy)
+                .with_seed(Some(*seed));
+
+            let (tour, stages) = pipeline.run(&problem);
+            let cost = problem.cost(&tour);
+
+            row.costs.push(cost);
+            row.elapsed
+                .push(stages.iter().map(|stage| stage.elapsed).sum());
+
+            if best.as_ref().is_none_or(|(best, _)| cost < *best) {
+                best = Some((cost, tour));
+            }
+        }
+
+        rows.push(row);
+    }
+
+    Comparison {
+        rows,
+        best: best.map_or(Tour::new(vec![]), |(_, tour)| tour),
+    }
+}
+
+impl Comparison {
+    // One line per pipeline, with the ratio of its mean cost to `lower_bound`
+    pub fn print(&self, quantity: &str, lower_bound: Cost) {
+        let width = self
+            .rows
+            .iter()
+            .map(|row| row.pipeline.len())
+            .max()
+            .unwrap_or(0)
+            .max("pipeline".len());
+
+        println!(
+            "{:<width$}  {:>12}  {:>12}  {:>12}  {:>10}  {:>6}",
+            "pipeline", "mean", "min", "max", "time", "ratio"
+        );
+        for row in self.rows.iter() {
+            println!(
+                "{:<width$}  {:>12.1}  {:>12.1}  {:>12.1}  {:>10}  {:>6.3}",
+                row.pipeline,
+                row.mean(),
+                row.min(),
+                row.max(),
+                format!("{:.2?}", row.mean_elapsed()),
+                row.mean() / lower_bound
+            );
+        }
+        println!(
+            "Ratios are mean {} to the 1-tree lower bound of {:.1}",
+            quantity, lower_bound
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        edge::{Spatial, TOLERANCE},
+        mst::MST,
+    };
+
+    #[derive(Debug, Clone)]
+    struct Point {
+        x: f64,
+        y: f64,
+    }
+
+    impl Edge for Point {
+        fn weight(&self, node: &Self) -> Cost {
+            ((self.x - node.x).powi(2) + (self.y - node.y).powi(2)).sqrt()
+        }
+    }
+
+    impl Spatial for Point {
+        fn position(&self) -> [f64; 2] {
+            [self.x, self.y]
+        }
+    }
+
+    #[test]
+    fn compares_pipelines() -> eyre::Result<()> {
+        let nodes: Vec<Point> = (0..30)
+            .map(|i| {
+                let i = i as f64;
+                Point {
+                    x: (i * 37.0) % 101.0,
+                    y: (i * 59.0) % 97.0,
+                }
+            })
+            .collect();
+        let matrix = DistanceMatrix::dense(&nodes);
+        let candidates = Candidates::new(&nodes, 10);
+
+        let pipelines = PIPELINES
+            .iter()
+            .map(|pipeline| pipeline.parse())
+            .collect::<eyre::Result<Vec<PipelineConfig>>>()?;
+        let comparison = compare(
+            &nodes,
+            &matrix,
+            &candidates,
+            Strategy::default(),
+            &pipelines,
+            &[1, 2, 3],
+        );
+
+        let mut mst = MST::new(nodes.clone()).with_matrix(matrix.clone());
+        mst.solve();
+        let lower_bound = mst.calculate_one_tree_cost();
+
+        assert_eq!(comparison.rows.len(), PIPELINES.len());
+        for row in comparison.rows.iter() {
+            assert!(lower_bound <= row.min());
+            assert!(row.min() <= row.mean() + TOLERANCE && row.mean() <= row.max() + TOLERANCE);
+        }
+
+        // Nearest neighbour does not use the seed
+        assert_eq!(comparison.rows[0].min(), comparison.rows[0].max());
+
+        let problem = Problem::new(&nodes, &matrix);
+        let best = comparison
+            .rows
+            .iter()
+            .map(Row::min)
+            .fold(Cost::INFINITY, Cost::min);
+        assert_eq!(problem.cost(&comparison.best), best);
+
+        Ok(())
+    }
+}
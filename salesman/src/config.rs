@@ -0,0 +1,369 @@
+use std::{collections::HashMap, fs, path::Path, str::FromStr};
+
+use eyre::eyre;
+use serde::{Deserialize, Serialize};
+
+// NOTE: Everything that decides a run, either from the command line or from a profile in a
+// salesman.toml. Values are kept as they are written and parsed once both have been merged.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    // Statues CSV, or a TSPLIB .tsp instance to solve as it is
+    pub input: Option<String>,
+    // Where to cache the distance matrix
+    pub matrix: Option<String>,
+    // OSM extract to walk or cycle through
+    pub streets: Option<String>,
+
+    // Starting points CSV, and the start and end picked from it
+    pub places: Option<String>,
+    pub start: Option<String>,
+    pub end: Option<String>,
+
+    // Statue filters: how many of the closest to keep (0 keeps every one), how far from the start
+    // in metres, and text the title or place has to contain
+    pub limit: Option<usize>,
+    pub within: Option<f64>,
+    pub search: Option<String>,
+
+    // Cost model
+    pub metric: Option<String>,
+    // "distance" or "time"
+    pub cost: Option<String>,
+    // "walking" or "cycling"
+    pub mode: Option<String>,
+    pub speed: Option<f64>,
+    pub climb: Option<f64>,
+    pub dwell: Option<f64>,
+    pub elevation: Option<String>,
+    pub gtfs: Option<String>,
+    pub date: Option<u32>,
+    pub departure: Option<String>,
+    pub docks: Option<String>,
+
+    // Solvers
+    pub pipeline: Option<String>,
+    // "first" or "best" improvement
+    pub strategy: Option<String>,
+    pub seed: Option<u64>,
+
+    // Compared by `compare`, each with `runs` seeds counting up from `seed`
+    pub pipelines: Option<Vec<String>>,
+    pub runs: Option<u64>,
+
+    pub output: Option<String>,
+    // TSPLIB file the instance is exported to, with the tour next to it as a .tour file
+    pub tsplib: Option<String>,
+    // GPX file the route is written to
+    pub gpx: Option<String>,
+    // GeoJSON and KML files the route is written to, with the spanning tree if `mst` is set
+    pub geojson: Option<String>,
+    pub kml: Option<String>,
+    pub mst: Option<bool>,
+    // SVG or PNG file the tour is drawn to, with the spanning tree if `mst` is set
+    pub render: Option<String>,
+    // Single HTML page with the stops, the distance of every leg and a map
+    pub html: Option<String>,
+    // CSV file the route is written to, with the statue columns and the distances along the way
+    pub csv: Option<String>,
+}
+
+// What is done with the input: a route planned through it, `compare` running several pipelines
+// over the same statues, or `validate` reporting what is wrong with it
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Command {
+    #[default]
+    Plan,
+    Compare,
+    Validate,
+}
+
+impl Profile {
+    // Reads the profile from the command line, removing every flag it knows from `args`. What is
+    // left are the positional arguments: an optional command, followed by the statues CSV (or a
+    // TSPLIB .tsp file), matrix cache and OSM extract.
+    // Pipelines to compare are separated by commas.
+    pub fn from_args(args: &mut Vec<String>) -> eyre::Result<(Command, Self)> {
+        let mut profile = Self {
+            places: take_flag(args, "--places")?,
+            start: take_flag(args, "--start")?,
+            end: take_flag(args, "--end")?,
+            limit: parse(take_flag(args, "--limit")?)?,
+            within: parse(take_flag(args, "--within")?)?,
+            search: take_flag(args, "--search")?,
+            metric: take_flag(args, "--metric")?,
+            speed: parse(take_flag(args, "--speed")?)?,
+            climb: parse(take_flag(args, "--climb")?)?,
+            dwell: parse(take_flag(args, "--dwell")?)?,
+            elevation: take_flag(args, "--elevation")?,
+            gtfs: take_flag(args, "--gtfs")?,
+            date: parse(take_flag(args, "--date")?)?,
+            departure: take_flag(args, "--departure")?,
+            docks: take_flag(args, "--docks")?,
+            pipeline: take_flag(args, "--pipeline")?,
+            strategy: take_flag(args, "--strategy")?,
+            seed: parse(take_flag(args, "--seed")?)?,
+            pipelines: take_flag(args, "--pipelines")?.map(|pipelines| {
+                pipelines
+                    .split(',')
+                    .map(|pipeline| pipeline.trim().to_string())
+                    .collect()
+            }),
+            runs: parse(take_flag(args, "--runs")?)?,
+            // `--format` is another name for `--output`
+            output: take_flag(args, "--output")?.or(take_flag(args, "--format")?),
+            tsplib: take_flag(args, "--tsplib")?,
+            gpx: take_flag(args, "--gpx")?,
+            geojson: take_flag(args, "--geojson")?,
+            kml: take_flag(args, "--kml")?,
+            render: take_flag(args, "--render")?,
+            html: take_flag(args, "--html")?,
+            csv: take_flag(args, "--csv")?,
+            ..Self::default()
+        };
+
+        if take_switch(args, "--time") {
+            profile.cost = Some("time".to_string());
+        }
+        if take_switch(args, "--cycle") {
+            profile.mode = Some("cycling".to_string());
+        }
+        if take_switch(args, "--mst") {
+            profile.mst = Some(true);
+        }
+
+        if let Some(flag) = args.iter().skip(1).find(|arg| arg.starts_with("--")) {
+            return Err(eyre!("Unknown flag {}", flag));
+        }
+
+        // Only looked for once the flags are gone, so that it can come before or after them
+        let command = match args.get(1).map(String::as_str) {
+            Some("compare") => Command::Compare,
+            Some("validate") => Command::Validate,
+            _ => Command::Plan,
+        };
+        if command != Command::Plan {
+            args.remove(1);
+        }
+
+        if args.len() > 4 {
+            return Err(eyre!("Unexpected arguments: {}", args[4..].join(" ")));
+        }
+        profile.input = args.get(1).cloned();
+        profile.matrix = args.get(2).cloned();
+        profile.streets = args.get(3).cloned();
+
+        Ok((command, profile))
+    }
+
+    // Every value that is missing here is taken from `fallback`
+    pub fn or(self, fallback: Self) -> Self {
+        Self {
+            input: self.input.or(fallback.input),
+            matrix: self.matrix.or(fallback.matrix),
+            streets: self.streets.or(fallback.streets),
+            places: self.places.or(fallback.places),
+            start: self.start.or(fallback.start),
+            end: self.end.or(fallback.end),
+            limit: self.limit.or(fallback.limit),
+            within: self.within.or(fallback.within),
+            search: self.search.or(fallback.search),
+            metric: self.metric.or(fallback.metric),
+            cost: self.cost.or(fallback.cost),
+            mode: self.mode.or(fallback.mode),
+            speed: self.speed.or(fallback.speed),
+            climb: self.climb.or(fallback.climb),
+            dwell: self.dwell.or(fallback.dwell),
+            elevation: self.elevation.or(fallback.elevation),
+            gtfs: self.gtfs.or(fallback.gtfs),
+            date: self.date.or(fallback.date),
+            departure: self.departure.or(fallback.departure),
+            docks: self.docks.or(fallback.docks),
+            pipeline: self.pipeline.or(fallback.pipeline),
+            strategy: self.strategy.or(fallback.strategy),
+            seed: self.seed.or(fallback.seed),
+            pipelines: self.pipelines.or(fallback.pipelines),
+            runs: self.runs.or(fallback.runs),
+            output: self.output.or(fallback.output),
+            tsplib: self.tsplib.or(fallback.tsplib),
+            gpx: self.gpx.or(fallback.gpx),
+            geojson: self.geojson.or(fallback.geojson),
+            kml: self.kml.or(fallback.kml),
+            mst: self.mst.or(fallback.mst),
+            render: self.render.or(fallback.render),
+            html: self.html.or(fallback.html),
+            csv: self.csv.or(fallback.csv),
+        }
+    }
+}
+
+// NOTE: A salesman.toml. Settings under [defaults] apply to every run, and each [profiles.<name>]
+// table overrides them when the profile is picked with `--profile <name>`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    defaults: Profile,
+    #[serde(default)]
+    profiles: HashMap<String, Profile>,
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    fn parse(toml: &str) -> eyre::Result<Self> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    pub fn profile(&self, name: Option<&str>) -> eyre::Result<Profile> {
+        let Some(name) = name else {
+            return Ok(self.defaults.clone());
+        };
+
+        match self.profiles.get(name) {
+            Some(profile) => Ok(profile.clone().or(self.defaults.clone())),
+            None => {
+                let mut names: Vec<&str> = self.profiles.keys().map(|name| name.as_str()).collect();
+                names.sort();
+                Err(eyre!(
+                    "Unknown profile {}, expected one of {}",
+                    name,
+                    names.join(", ")
+                ))
+            }
+        }
+    }
+}
+
+// How the result of a run is printed
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Format {
+    // Costs of the solvers, the path and the itinerary
+    #[default]
+    Text,
+    // Only the names of the stops in visiting order, one per line
+    Names,
+    // Everything about the run as one JSON object, see `Report`
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "names" => Ok(Self::Names),
+            "json" => Ok(Self::Json),
+            _ => Err(eyre!(
+                "Unknown output format {}, expected text, names or json",
+                s
+            )),
+        }
+    }
+}
+
+fn parse<T: FromStr>(value: Option<String>) -> eyre::Result<Option<T>>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    Ok(match value {
+        Some(value) => Some(value.parse()?),
+        None => None,
+    })
+}
+
+// Removes `flag` and the value following it from the arguments
+pub fn take_flag(args: &mut Vec<String>, flag: &str) -> eyre::Result<Option<String>> {
+    match args.iter().position(|arg| arg == flag) {
+        Some(i) if i + 1 < args.len() => {
+            let value = args.remove(i + 1);
+            args.remove(i);
+            Ok(Some(value))
+        }
+        Some(_) => Err(eyre!("{} needs a value", flag)),
+        None => Ok(None),
+    }
+}
+
+// Removes `flag` from the arguments, telling if it was there
+fn take_switch(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|arg| arg == flag) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        [defaults]
+        input = "statues.csv"
+        limit = 25
+
+        [profiles.afternoon-walk]
+        limit = 12
+        cost = "time"
+        pipeline = "nn -> or_opt -> three_opt"
+        seed = 7
+    "#;
+
+    #[test]
+    fn profiles_override_defaults() -> eyre::Result<()> {
+        let config = Config::parse(CONFIG)?;
+
+        let profile = config.profile(Some("afternoon-walk"))?;
+        assert_eq!(profile.input.as_deref(), Some("statues.csv"));
+        assert_eq!(profile.limit, Some(12));
+        assert_eq!(profile.seed, Some(7));
+
+        assert_eq!(config.profile(None)?.limit, Some(25));
+        assert!(config.profile(Some("morning-run")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn command_line_wins() -> eyre::Result<()> {
+        let mut args: Vec<String> = ["salesman", "--limit", "5", "--time", "other.csv"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let (command, cli) = Profile::from_args(&mut args)?;
+        assert_eq!(command, Command::Plan);
+
+        let profile = cli.or(Config::parse(CONFIG)?.profile(None)?);
+        assert_eq!(profile.input.as_deref(), Some("other.csv"));
+        assert_eq!(profile.limit, Some(5));
+        assert_eq!(profile.cost.as_deref(), Some("time"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn commands_after_flags() -> eyre::Result<()> {
+        let mut args: Vec<String> = ["salesman", "--limit", "5", "validate", "other.csv"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let (command, cli) = Profile::from_args(&mut args)?;
+
+        assert_eq!(command, Command::Validate);
+        assert_eq!(cli.input.as_deref(), Some("other.csv"));
+        assert_eq!(cli.limit, Some(5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_settings() {
+        assert!(Config::parse("[defaults]\nlimt = 3").is_err());
+    }
+}
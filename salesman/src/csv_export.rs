@@ -0,0 +1,119 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::edge::{Cost, Geographic, NodeLatLon};
+
+// NOTE: One stop of the route, with the columns of the statues CSV first so that the file can be
+// read the same way, followed by where the stop comes in the route. Starting points only have a
+// title and coordinates.
+#[derive(Debug, Serialize)]
+struct Row<'a> {
+    title: &'a str,
+    r#where: &'a str,
+    link: &'a str,
+    lat: f64,
+    lon: f64,
+    address: &'a str,
+    order: usize,
+    // Whole metres from the previous stop, and from the start
+    leg_m: u64,
+    cumulative_m: u64,
+    // Arrival, only when the route is timed
+    eta: Option<&'a str>,
+}
+
+// Writes the stops in visiting order with the length of every leg in `distances`, and the arrival
+// at every stop in `etas` if there are any. A round trip ends with the start again.
+pub fn save(
+    path: impl AsRef<Path>,
+    stops: &[NodeLatLon],
+    order: &[usize],
+    distances: &[Cost],
+    etas: Option<&[String]>,
+) -> eyre::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    write(&mut writer, stops, order, distances, etas)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn write<W: std::io::Write>(
+    writer: &mut csv::Writer<W>,
+    stops: &[NodeLatLon],
+    order: &[usize],
+    distances: &[Cost],
+    etas: Option<&[String]>,
+) -> eyre::Result<()> {
+    let mut cumulative = 0.0;
+
+    for (p, i) in order.iter().enumerate() {
+        let stop = &stops[*i];
+        let latlon = stop.latlon();
+        let leg = match p {
+            0 => 0.0,
+            _ => distances[p - 1],
+        };
+        cumulative += leg;
+
+        let (r#where, link, address) = match stop {
+            NodeLatLon::Statue(statue) => (statue.r#where(), statue.link(), statue.address()),
+            NodeLatLon::StartingPoint(_) => ("", "", ""),
+        };
+
+        writer.serialize(Row {
+            title: stop.name(),
+            r#where,
+            link,
+            lat: latlon.lat(),
+            lon: latlon.lon(),
+            address,
+            order: p + 1,
+            leg_m: leg.round() as u64,
+            cumulative_m: cumulative.round() as u64,
+            eta: etas.map(|etas| etas[p].as_str()),
+        })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statue::{test_stops, MaybeStatue, Statue};
+
+    #[test]
+    fn reads_back_as_statues() -> eyre::Result<()> {
+        let stops = test_stops();
+
+        let mut writer = csv::Writer::from_writer(vec![]);
+        write(&mut writer, &stops, &[0, 1, 0], &[420.0, 380.0], None)?;
+        let written = String::from_utf8(writer.into_inner()?)?;
+
+        let mut lines = written.lines();
+        assert_eq!(
+            lines.next(),
+            Some("title,where,link,lat,lon,address,order,leg_m,cumulative_m,eta")
+        );
+        assert_eq!(
+            lines.nth(1),
+            Some("Sinnataggen & co,Vigelandsparken,https://example.com/sinnataggen,59.927,10.7004,Nobels gate 32,2,420,420,")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("Grønland T-bane,,,59.9127,10.7607,,3,380,800,")
+        );
+
+        // Statues keep their columns, the start has no address and is left out as before
+        let statues: Vec<Statue> = csv::Reader::from_reader(written.as_bytes())
+            .deserialize::<MaybeStatue>()
+            .filter_map(|row| row.ok()?.try_into().ok())
+            .collect();
+        assert_eq!(statues.len(), 1);
+        assert_eq!(statues[0].title(), "Sinnataggen & co");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+use std::{collections::HashMap, fmt::Debug, fs, path::Path, rc::Rc};
+
+use serde::Deserialize;
+
+use crate::{
+    edge::{Cost, Geographic, Spatial},
+    kdtree::KdTree,
+    latlon::LatLon,
+    precomputed::Precomputed,
+    travel::{Elevation, TravelTime},
+};
+
+// Minutes it takes to take a bike out of a dock, and again to return it
+const DOCKING: f64 = 1.0;
+
+// Furthest we walk to or from a dock, in metres
+const MAX_ACCESS: f64 = 500.0;
+
+// Number of closest docks considered around every node
+const NEARBY_DOCKS: usize = 3;
+
+#[derive(Debug, Deserialize)]
+struct Station {
+    name: String,
+    lat: f64,
+    lon: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct StationList {
+    stations: Vec<Station>,
+}
+
+// NOTE: Either the station_information.json from a GBFS feed, like the one Oslo Bysykkel
+// publishes, or just the list of stations in it.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum StationFile {
+    Gbfs { data: StationList },
+    List(Vec<Station>),
+}
+
+// Docking stations of a bike share, used to ride between nodes that are far enough apart for
+// walking to a dock, cycling and walking from a dock to beat walking the whole way.
+#[derive(Debug, Clone)]
+pub struct Docks {
+    stations: Vec<LatLon>,
+    names: Rc<Vec<String>>,
+    tree: KdTree,
+}
+
+impl Docks {
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    fn parse(json: &str) -> eyre::Result<Self> {
+        let stations = match serde_json::from_str(json)? {
+            StationFile::Gbfs { data } => data.stations,
+            StationFile::List(stations) => stations,
+        };
+
+        let names = stations
+            .iter()
+            .map(|station| station.name.clone())
+            .collect();
+        let stations: Vec<LatLon> = stations
+            .iter()
+            .map(|station| LatLon::new(station.lat, station.lon))
+            .collect();
+        let tree = KdTree::new(stations.iter().map(|station| station.position()).collect());
+
+        Ok(Self {
+            stations,
+            names: Rc::new(names),
+            tree,
+        })
+    }
+
+    // Docks within walking distance, closest first
+    fn nearby(&self, latlon: &LatLon) -> Vec<usize> {
+        self.tree
+            .nearest(latlon.position(), NEARBY_DOCKS)
+            .into_iter()
+            .filter(|dock| latlon.calculate_distance_to(&self.stations[*dock]) <= MAX_ACCESS)
+            .collect()
+    }
+
+    // Travel time in minutes between every pair of nodes, either walking the whole way or riding
+    // between the docks close to them. `walk` and `cycle` give the distances in metres between
+    // every pair of places, row by row, so that they can come from the street network of that
+    // mode or a straight line.
+    pub fn ride<N: Geographic>(
+        &self,
+        nodes: Vec<N>,
+        walking: TravelTime,
+        cycling: TravelTime,
+        elevation: Option<&Elevation>,
+        walk: impl Fn(&[LatLon]) -> Vec<Cost>,
+        cycle: impl Fn(&[LatLon]) -> Vec<Cost>,
+    ) -> (Vec<Precomputed<N>>, Rides) {
+        let n = nodes.len();
+
+        // The nodes come first, followed by the docks close to any of them
+        let mut places: Vec<LatLon> = nodes.iter().map(|node| node.latlon()).collect();
+        let mut place_of_dock: HashMap<usize, usize> = HashMap::new();
+        let nearby: Vec<Vec<usize>> = (0..n)
+            .map(|i| {
+                self.nearby(&places[i])
+                    .into_iter()
+                    .map(|dock| {
+                        *place_of_dock.entry(dock).or_insert_with(|| {
+                            places.push(self.stations[dock].clone());
+                            places.len() - 1
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+        let dock_of_place: HashMap<usize, usize> = place_of_dock
+            .iter()
+            .map(|(dock, place)| (*place, *dock))
+            .collect();
+
+        let m = places.len();
+        let walked = walk(&places);
+        let cycled = cycle(&places);
+        let heights: Vec<f64> = places
+            .iter()
+            .map(|place| elevation.map_or(0.0, |elevation| elevation.at(place.position())))
+            .collect();
+        let time = |travel: &TravelTime, metres: &[Cost], p: usize, q: usize| {
+            travel.minutes(metres[p * m + q], heights[q] - heights[p])
+        };
+
+        let mut minutes = vec![0.0; n * n];
+        let mut metres = vec![0.0; n * n];
+        let mut via = vec![None; n * n];
+
+        for i in 0..n {
+            for j in 0..n {
+                if i == j {
+                    continue;
+                }
+
+                minutes[i * n + j] = time(&walking, &walked, i, j);
+                metres[i * n + j] = walked[i * m + j];
+
+                for from in nearby[i].iter() {
+                    for to in nearby[j].iter().filter(|to| *to != from) {
+                        let riding = time(&walking, &walked, i, *from)
+                            + DOCKING
+                            + time(&cycling, &cycled, *from, *to)
+                            + DOCKING
+                            + time(&walking, &walked, *to, j);
+
+                        if riding < minutes[i * n + j] {
+                            minutes[i * n + j] = riding;
+                            metres[i * n + j] =
+                                walked[i * m + from] + cycled[from * m + to] + walked[to * m + j];
+                            via[i * n + j] = Some((dock_of_place[from], dock_of_place[to]));
+                        }
+                    }
+                }
+            }
+        }
+
+        let rides = Rides {
+            size: n,
+            via,
+            metres,
+            names: self.names.clone(),
+        };
+
+        (Precomputed::wrap(nodes, minutes), rides)
+    }
+}
+
+// NOTE: Docks the bike is taken from and returned to between every pair of nodes, if riding
+// beats walking.
+#[derive(Debug, Clone)]
+pub struct Rides {
+    size: usize,
+    via: Vec<Option<(usize, usize)>>,
+    // Walked, or walked and cycled, on the way that is taken
+    metres: Vec<Cost>,
+    names: Rc<Vec<String>>,
+}
+
+impl Rides {
+    // Names of the docks to ride between on the way from node `i` to node `j`
+    pub fn docks(&self, i: usize, j: usize) -> Option<(&str, &str)> {
+        self.via[i * self.size + j]
+            .map(|(from, to)| (self.names[from].as_str(), self.names[to].as_str()))
+    }
+
+    // Distance in metres covered on the way from node `i` to node `j`
+    pub fn metres(&self, i: usize, j: usize) -> Cost {
+        self.metres[i * self.size + j]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edge::Edge;
+
+    // Two docks 5 km apart, each right next to a node, and one far away from both
+    const STATIONS: &str = r#"{
+        "last_updated": 1760000000,
+        "data": {
+            "stations": [
+                { "station_id": "1", "name": "West", "lat": 59.91, "lon": 10.7005, "capacity": 20 },
+                { "station_id": "2", "name": "East", "lat": 59.91, "lon": 10.7895, "capacity": 20 },
+                { "station_id": "3", "name": "North", "lat": 59.99, "lon": 10.75, "capacity": 20 }
+            ]
+        }
+    }"#;
+
+    fn straight(places: &[LatLon]) -> Vec<Cost> {
+        places
+            .iter()
+            .flat_map(|a| places.iter().map(|b| a.calculate_distance_to(b)))
+            .collect()
+    }
+
+    #[test]
+    fn rides_between_docks() -> eyre::Result<()> {
+        let docks = Docks::parse(STATIONS)?;
+        let nodes = vec![
+            LatLon::new(59.91, 10.70),
+            LatLon::new(59.91, 10.79),
+            LatLon::new(59.9105, 10.70),
+        ];
+
+        let walking = TravelTime::default();
+        let cycling = TravelTime::cycling();
+        let (docked, rides) = docks.ride(nodes.clone(), walking, cycling, None, straight, straight);
+        let stations = &docks.stations;
+
+        assert_eq!(rides.docks(0, 1), Some(("West", "East")));
+        assert_eq!(rides.docks(1, 0), Some(("East", "West")));
+        assert!(docked[0].weight(&docked[1]) < 30.0);
+
+        // Along the ride, up to the first dock and back from the second one
+        let places = [
+            nodes[0].clone(),
+            stations[0].clone(),
+            stations[1].clone(),
+            nodes[1].clone(),
+        ];
+        let ridden: Cost = places
+            .windows(2)
+            .map(|pair| pair[0].calculate_distance_to(&pair[1]))
+            .sum();
+        assert!((rides.metres(0, 1) - ridden).abs() < 1e-6);
+
+        // Close enough to walk
+        assert_eq!(rides.docks(0, 2), None);
+        assert_eq!(
+            rides.metres(0, 2),
+            nodes[0].calculate_distance_to(&nodes[2])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_a_plain_list() -> eyre::Result<()> {
+        let docks = Docks::parse(r#"[{ "name": "West", "lat": 59.91, "lon": 10.7005 }]"#)?;
+        assert_eq!(docks.names.as_slice(), ["West"]);
+
+        Ok(())
+    }
+}
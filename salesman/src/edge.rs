@@ -3,9 +3,34 @@ use crate::{
     statue::Statue,
 };
 
+// Cost of an edge or a tour, in whatever unit the nodes weigh in, e.g. metres between coordinates.
+// Every solver works in this type, so changing the precision only happens here.
+pub type Cost = f64;
+
+// Smallest decrease in cost the solvers count as an improvement. Anything below it is rounding
+// noise, and accepting it could make a solver swap back and forth between equally good tours.
+pub const TOLERANCE: Cost = 1e-6;
+
 // NOTE: Implementing this trait gives a way to calculate weight of edge between two nodes.
 pub trait Edge {
-    fn weight(&self, node: &Self) -> f32;
+    // Set to false when the weight from a to b can differ from the weight from b to a, e.g. uphill
+    // versus downhill. The solvers then also account for the edges inside the segments they reverse.
+    const SYMMETRIC: bool = true;
+
+    fn weight(&self, node: &Self) -> Cost;
+}
+
+// NOTE: Implementing this trait places a node in the plane, which lets the solvers find its
+// nearest neighbours through a k-d tree instead of comparing it against every other node.
+// Distances in the plane only decide which nodes are candidates, the cost still comes from `Edge`.
+pub trait Spatial {
+    fn position(&self) -> [f64; 2];
+}
+
+// NOTE: Implementing this trait puts a node on the globe, which lets a `DistanceMetric` measure
+// the distance between nodes instead of relying on the weight the node calculates itself.
+pub trait Geographic {
+    fn latlon(&self) -> LatLon;
 }
 
 #[derive(Debug, Clone)]
@@ -15,6 +40,15 @@ pub enum NodeLatLon {
 }
 
 impl NodeLatLon {
+    pub fn name(&self) -> &str {
+        match self {
+            NodeLatLon::StartingPoint(starting_point) => starting_point.name(),
+            NodeLatLon::Statue(statue) => statue.title(),
+        }
+    }
+}
+
+impl Geographic for NodeLatLon {
     fn latlon(&self) -> LatLon {
         match self {
             NodeLatLon::StartingPoint(starting_point) => starting_point.latlon().clone(),
@@ -24,7 +58,13 @@ impl NodeLatLon {
 }
 
 impl Edge for NodeLatLon {
-    fn weight(&self, node: &Self) -> f32 {
+    fn weight(&self, node: &Self) -> Cost {
         self.latlon().weight(&node.latlon())
     }
 }
+
+impl Spatial for NodeLatLon {
+    fn position(&self) -> [f64; 2] {
+        self.latlon().position()
+    }
+}
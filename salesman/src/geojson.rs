@@ -0,0 +1,126 @@
+use std::{fs, path::Path};
+
+use serde_json::{json, Value};
+
+use crate::{
+    edge::{Geographic, NodeLatLon},
+    tour,
+};
+
+// Writes the stops visited in `order` as a GeoJSON FeatureCollection: a point for every stop with
+// its place in the tour and what is known about the statue, and a line string for the tour.
+// The edges of `tree` are added as a multi line string if given.
+pub fn save(
+    path: impl AsRef<Path>,
+    stops: &[NodeLatLon],
+    order: &[usize],
+    tree: Option<&[(usize, usize)]>,
+) -> eyre::Result<()> {
+    Ok(fs::write(
+        path,
+        serde_json::to_string_pretty(&collection(stops, order, tree))?,
+    )?)
+}
+
+fn collection(stops: &[NodeLatLon], order: &[usize], tree: Option<&[(usize, usize)]>) -> Value {
+    let mut features: Vec<Value> = tour::visits(order)
+        .iter()
+        .enumerate()
+        .map(|(p, i)| {
+            let stop = &stops[*i];
+            let properties = match stop {
+                NodeLatLon::StartingPoint(_) => json!({
+                    "kind": "start",
+                    "order": p,
+                    "name": stop.name(),
+                }),
+                NodeLatLon::Statue(statue) => json!({
+                    "kind": "statue",
+                    "order": p,
+                    "name": statue.title(),
+                    "where": statue.r#where(),
+                    "address": statue.address(),
+                    "link": statue.link(),
+                }),
+            };
+
+            json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": coordinates(stop) },
+                "properties": properties,
+            })
+        })
+        .collect();
+
+    features.push(json!({
+        "type": "Feature",
+        "geometry": {
+            "type": "LineString",
+            "coordinates": order.iter().map(|i| coordinates(&stops[*i])).collect::<Vec<_>>(),
+        },
+        "properties": { "kind": "tour" },
+    }));
+
+    if let Some(tree) = tree {
+        features.push(json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "MultiLineString",
+                "coordinates": tree
+                    .iter()
+                    .map(|(a, b)| [coordinates(&stops[*a]), coordinates(&stops[*b])])
+                    .collect::<Vec<_>>(),
+            },
+            "properties": { "kind": "mst" },
+        }));
+    }
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+// GeoJSON positions are longitude first
+fn coordinates(stop: &NodeLatLon) -> [f64; 2] {
+    let latlon = stop.latlon();
+    [latlon.lon(), latlon.lat()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statue::test_stops;
+
+    #[test]
+    fn collects_stops_tour_and_tree() {
+        let stops = test_stops();
+
+        let with_tree = collection(&stops, &[0, 1, 0], Some(&[(0, 1)]));
+        let features = with_tree["features"].as_array().unwrap();
+
+        assert_eq!(features.len(), 4);
+        assert_eq!(features[1]["properties"]["order"], 1);
+        assert_eq!(features[1]["properties"]["address"], "Nobels gate 32");
+        assert_eq!(
+            features[1]["geometry"]["coordinates"],
+            json!([10.7004, 59.9270])
+        );
+        assert_eq!(
+            features[2]["geometry"]["coordinates"]
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(features[3]["properties"]["kind"], "mst");
+
+        assert_eq!(
+            collection(&stops, &[0, 1, 0], None)["features"]
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+}
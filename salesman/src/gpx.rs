@@ -0,0 +1,122 @@
+use std::{fs, io, path::Path};
+
+use quick_xml::{
+    events::{BytesDecl, BytesText, Event},
+    Writer,
+};
+
+use crate::{
+    edge::{Geographic, NodeLatLon},
+    tour,
+};
+
+// Writes the stops visited in `order` as a GPX 1.1 route, with every stop also as a waypoint so
+// that apps which only show waypoints still get the names. Statues are described by their
+// address and link back to where they were found.
+pub fn save(
+    path: impl AsRef<Path>,
+    name: &str,
+    stops: &[NodeLatLon],
+    order: &[usize],
+) -> eyre::Result<()> {
+    Ok(fs::write(path, write(name, stops, order)?)?)
+}
+
+fn write(name: &str, stops: &[NodeLatLon], order: &[usize]) -> io::Result<Vec<u8>> {
+    let mut writer = Writer::new_with_indent(vec![], b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+
+    writer
+        .create_element("gpx")
+        .with_attribute(("version", "1.1"))
+        .with_attribute(("creator", "salesman"))
+        .with_attribute(("xmlns", "http://www.topografix.com/GPX/1/1"))
+        .write_inner_content(|writer| {
+            writer
+                .create_element("metadata")
+                .write_inner_content(|writer| {
+                    writer
+                        .create_element("name")
+                        .write_text_content(BytesText::new(name))?;
+                    Ok(())
+                })?;
+
+            for i in tour::visits(order) {
+                point(writer, "wpt", &stops[*i])?;
+            }
+
+            writer.create_element("rte").write_inner_content(|writer| {
+                writer
+                    .create_element("name")
+                    .write_text_content(BytesText::new(name))?;
+                for i in order {
+                    point(writer, "rtept", &stops[*i])?;
+                }
+                Ok(())
+            })?;
+
+            Ok(())
+        })?;
+
+    Ok(writer.into_inner())
+}
+
+// A waypoint or route point, with its children in the order the GPX schema expects them
+fn point(writer: &mut Writer<Vec<u8>>, element: &str, stop: &NodeLatLon) -> io::Result<()> {
+    let latlon = stop.latlon();
+
+    writer
+        .create_element(element)
+        .with_attribute(("lat", latlon.lat().to_string().as_str()))
+        .with_attribute(("lon", latlon.lon().to_string().as_str()))
+        .write_inner_content(|writer| {
+            writer
+                .create_element("name")
+                .write_text_content(BytesText::new(stop.name()))?;
+
+            if let NodeLatLon::Statue(statue) = stop {
+                writer
+                    .create_element("desc")
+                    .write_text_content(BytesText::new(statue.address()))?;
+                writer
+                    .create_element("link")
+                    .with_attribute(("href", statue.link()))
+                    .write_empty()?;
+            }
+
+            Ok(())
+        })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statue::test_stops;
+    use quick_xml::Reader;
+
+    #[test]
+    fn writes_route_and_waypoints() -> eyre::Result<()> {
+        let stops = test_stops();
+
+        let gpx = String::from_utf8(write("Tour", &stops, &[0, 1, 0])?)?;
+        assert!(gpx.contains("<name>Sinnataggen &amp; co</name>"));
+        assert!(gpx.contains("<desc>Nobels gate 32</desc>"));
+        assert!(gpx.contains(r#"<link href="https://example.com/sinnataggen"/>"#));
+
+        let mut reader = Reader::from_str(&gpx);
+        let (mut waypoints, mut route) = (0, 0);
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) if e.name().as_ref() == b"wpt" => waypoints += 1,
+                Event::Start(e) if e.name().as_ref() == b"rtept" => route += 1,
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        assert_eq!((waypoints, route), (2, 3));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use std::{fmt::Write as _, fs, path::Path};
+
+use quick_xml::escape::escape;
+
+use crate::{
+    edge::{Cost, NodeLatLon},
+    latlon::distance,
+    render, tour,
+};
+
+const STYLE: &str =
+    "body { font-family: sans-serif; max-width: 800px; margin: 2em auto; padding: 0 1em; }
+svg { max-width: 100%; height: auto; border: 1px solid #ddd; }
+table { border-collapse: collapse; width: 100%; margin-top: 1em; }
+th, td { text-align: left; padding: 0.3em 0.5em; border-bottom: 1px solid #eee; }
+td.distance { text-align: right; white-space: nowrap; }";
+
+// Writes a page that works without anything else: the map of the tour, and a table with the stops
+// in visiting order, linking to where every statue was found. `legs` holds the distance in metres
+// of every leg, from one stop in `order` to the next.
+pub fn save(
+    path: impl AsRef<Path>,
+    title: &str,
+    stops: &[NodeLatLon],
+    order: &[usize],
+    legs: &[Cost],
+) -> eyre::Result<()> {
+    Ok(fs::write(path, page(title, stops, order, legs))?)
+}
+
+fn page(title: &str, stops: &[NodeLatLon], order: &[usize], legs: &[Cost]) -> String {
+    let title = escape(title);
+    let total: Cost = legs.iter().sum();
+    // A round trip lists the start again at the end, which is not another stop
+    let visits = tour::visits(order).len();
+
+    let mut html = String::new();
+    // Writing to a String cannot fail
+    let _ = writeln!(html, "<!DOCTYPE html>");
+    let _ = writeln!(html, r#"<html lang="en">"#);
+    let _ = writeln!(
+        html,
+        r#"<head><meta charset="utf-8"><title>{}</title><style>{}</style></head>"#,
+        title, STYLE
+    );
+    let _ = writeln!(html, "<body>");
+    let _ = writeln!(html, "<h1>{}</h1>", title);
+    let _ = writeln!(
+        html,
+        "<p>{} stops, {} in total</p>",
+        visits,
+        distance(total)
+    );
+    html.push_str(&render::svg(stops, order, None));
+
+    let _ = writeln!(html, "<table>");
+    let _ = writeln!(
+        html,
+        "<tr><th>#</th><th>Stop</th><th>Address</th><th>Leg</th><th>Total</th></tr>"
+    );
+
+    let mut cumulative = 0.0;
+    for (p, i) in order.iter().enumerate() {
+        let stop = &stops[*i];
+        let leg = match p {
+            0 => None,
+            _ => legs.get(p - 1).copied(),
+        };
+        cumulative += leg.unwrap_or(0.0);
+
+        let number = if p < visits {
+            (p + 1).to_string()
+        } else {
+            String::new()
+        };
+        let (name, address) = match stop {
+            NodeLatLon::Statue(statue) => (
+                format!(
+                    r#"<a href="{}">{}</a>"#,
+                    escape(statue.link()),
+                    escape(statue.title())
+                ),
+                escape(statue.address()).to_string(),
+            ),
+            NodeLatLon::StartingPoint(_) => (escape(stop.name()).to_string(), String::new()),
+        };
+
+        let _ = writeln!(
+            html,
+            r#"<tr><td>{}</td><td>{}</td><td>{}</td><td class="distance">{}</td><td class="distance">{}</td></tr>"#,
+            number,
+            name,
+            address,
+            leg.map(distance).unwrap_or_default(),
+            distance(cumulative)
+        );
+    }
+
+    let _ = writeln!(html, "</table>");
+    let _ = writeln!(html, "</body>");
+    let _ = writeln!(html, "</html>");
+
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statue::test_stops;
+
+    #[test]
+    fn lists_stops_with_distances() {
+        let stops = test_stops();
+
+        let html = page("Tour", &stops, &[0, 1, 0], &[420.0, 1380.0]);
+
+        assert!(html.contains("<p>2 stops, 1.8 km in total</p>"));
+        assert!(
+            html.contains(r#"<a href="https://example.com/sinnataggen">Sinnataggen &amp; co</a>"#)
+        );
+        assert!(html.contains(r#"<td class="distance">420 m</td><td class="distance">420 m</td>"#));
+        assert!(
+            html.contains(r#"<td class="distance">1.4 km</td><td class="distance">1.8 km</td>"#)
+        );
+        assert_eq!(html.matches("<svg").count(), 1);
+    }
+}
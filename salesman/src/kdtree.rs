@@ -0,0 +1,160 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+// k-d tree over points in the plane.
+// Used to look up the k nearest neighbours of a node without scanning every pair.
+#[derive(Debug, Clone)]
+pub struct KdTree {
+    points: Vec<[f64; 2]>,
+    // Indices into `points`, ordered so that the median of every range [lo, hi) sits at its middle
+    // and splits the range on the axis given by the depth of the range.
+    tree: Vec<usize>,
+}
+
+// NOTE: Used internally while searching, ordered by distance so the heap keeps the k best.
+#[derive(Debug, Clone, Copy)]
+struct Found {
+    dist: f64,
+    index: usize,
+}
+
+impl PartialEq for Found {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Found {}
+
+impl PartialOrd for Found {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Found {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.dist
+            .total_cmp(&other.dist)
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+impl KdTree {
+    pub fn new(points: Vec<[f64; 2]>) -> Self {
+        let mut tree: Vec<usize> = (0..points.len()).collect();
+        Self::build(&points, &mut tree, 0);
+
+        Self { points, tree }
+    }
+
+    fn build(points: &[[f64; 2]], range: &mut [usize], depth: usize) {
+        if range.len() <= 1 {
+            return;
+        }
+
+        let axis = depth % 2;
+        let mid = range.len() / 2;
+
+        range.select_nth_unstable_by(mid, |a, b| points[*a][axis].total_cmp(&points[*b][axis]));
+
+        let (left, right) = range.split_at_mut(mid);
+        Self::build(points, left, depth + 1);
+        Self::build(points, &mut right[1..], depth + 1);
+    }
+
+    // Returns the indices of the k points closest to `target`, closest first.
+    pub fn nearest(&self, target: [f64; 2], k: usize) -> Vec<usize> {
+        if k == 0 {
+            return vec![];
+        }
+
+        let mut heap: BinaryHeap<Found> = BinaryHeap::with_capacity(k + 1);
+        self.search(target, k, 0, self.tree.len(), 0, &mut heap);
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|found| found.index)
+            .collect()
+    }
+
+    fn search(
+        &self,
+        target: [f64; 2],
+        k: usize,
+        lo: usize,
+        hi: usize,
+        depth: usize,
+        heap: &mut BinaryHeap<Found>,
+    ) {
+        if lo >= hi {
+            return;
+        }
+
+        let mid = lo + (hi - lo) / 2;
+        let index = self.tree[mid];
+        let point = self.points[index];
+
+        let dx = point[0] - target[0];
+        let dy = point[1] - target[1];
+
+        heap.push(Found {
+            dist: dx * dx + dy * dy,
+            index,
+        });
+        if heap.len() > k {
+            heap.pop();
+        }
+
+        let axis = depth % 2;
+        let diff = target[axis] - point[axis];
+
+        // Visit the side of the split containing the target first,
+        // the other side can only be skipped once we have k points closer than the split.
+        let (near, far) = if diff < 0.0 {
+            ((lo, mid), (mid + 1, hi))
+        } else {
+            ((mid + 1, hi), (lo, mid))
+        };
+
+        self.search(target, k, near.0, near.1, depth + 1, heap);
+
+        let worst = heap.peek().map(|found| found.dist).unwrap_or(f64::INFINITY);
+        if heap.len() < k || diff * diff <= worst {
+            self.search(target, k, far.0, far.1, depth + 1, heap);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let points: Vec<[f64; 2]> = (0..200)
+            .map(|i| {
+                let i = i as f64;
+                [(i * 37.0) % 101.0, (i * 59.0) % 97.0]
+            })
+            .collect();
+
+        let tree = KdTree::new(points.clone());
+
+        for target in [[0.0, 0.0], [50.5, 48.0], [100.0, 3.0], [17.0, 90.0]] {
+            let mut brute: Vec<(f64, usize)> = points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    let dx = p[0] - target[0];
+                    let dy = p[1] - target[1];
+                    (dx * dx + dy * dy, i)
+                })
+                .collect();
+            brute.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+            let expected: Vec<usize> = brute.iter().take(8).map(|(_, i)| *i).collect();
+
+            assert_eq!(tree.nearest(target, 8), expected);
+        }
+    }
+}
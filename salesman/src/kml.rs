@@ -0,0 +1,139 @@
+use std::{fs, io, path::Path};
+
+use quick_xml::{
+    events::{BytesDecl, BytesText, Event},
+    Writer,
+};
+
+use crate::{
+    edge::{Geographic, NodeLatLon},
+    tour,
+};
+
+// Writes the stops visited in `order` as KML, e.g. for Google My Maps: a placemark for every stop,
+// numbered in visiting order, and one for the tour. The edges of `tree` are added as a placemark
+// of their own if given.
+pub fn save(
+    path: impl AsRef<Path>,
+    name: &str,
+    stops: &[NodeLatLon],
+    order: &[usize],
+    tree: Option<&[(usize, usize)]>,
+) -> eyre::Result<()> {
+    Ok(fs::write(path, write(name, stops, order, tree)?)?)
+}
+
+fn write(
+    name: &str,
+    stops: &[NodeLatLon],
+    order: &[usize],
+    tree: Option<&[(usize, usize)]>,
+) -> io::Result<Vec<u8>> {
+    let mut writer = Writer::new_with_indent(vec![], b' ', 2);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+
+    writer
+        .create_element("kml")
+        .with_attribute(("xmlns", "http://www.opengis.net/kml/2.2"))
+        .write_inner_content(|writer| {
+            writer
+                .create_element("Document")
+                .write_inner_content(|writer| {
+                    text(writer, "name", name)?;
+
+                    for (p, i) in tour::visits(order).iter().enumerate() {
+                        let stop = &stops[*i];
+                        writer
+                            .create_element("Placemark")
+                            .write_inner_content(|writer| {
+                                text(writer, "name", &format!("{}. {}", p + 1, stop.name()))?;
+                                if let NodeLatLon::Statue(statue) = stop {
+                                    text(
+                                        writer,
+                                        "description",
+                                        &format!("{}\n{}", statue.address(), statue.link()),
+                                    )?;
+                                }
+                                writer
+                                    .create_element("Point")
+                                    .write_inner_content(|writer| {
+                                        text(writer, "coordinates", &coordinates(stop))
+                                    })?;
+                                Ok(())
+                            })?;
+                    }
+
+                    writer
+                        .create_element("Placemark")
+                        .write_inner_content(|writer| {
+                            text(writer, "name", "Tour")?;
+                            line(writer, order.iter().map(|i| &stops[*i]))
+                        })?;
+
+                    if let Some(tree) = tree {
+                        writer
+                            .create_element("Placemark")
+                            .write_inner_content(|writer| {
+                                text(writer, "name", "Minimum spanning tree")?;
+                                writer.create_element("MultiGeometry").write_inner_content(
+                                    |writer| {
+                                        for (a, b) in tree {
+                                            line(writer, [&stops[*a], &stops[*b]].into_iter())?;
+                                        }
+                                        Ok(())
+                                    },
+                                )?;
+                                Ok(())
+                            })?;
+                    }
+
+                    Ok(())
+                })?;
+            Ok(())
+        })?;
+
+    Ok(writer.into_inner())
+}
+
+fn text(writer: &mut Writer<Vec<u8>>, element: &str, content: &str) -> io::Result<()> {
+    writer
+        .create_element(element)
+        .write_text_content(BytesText::new(content))?;
+    Ok(())
+}
+
+fn line<'a>(
+    writer: &mut Writer<Vec<u8>>,
+    stops: impl Iterator<Item = &'a NodeLatLon>,
+) -> io::Result<()> {
+    let coordinates: Vec<String> = stops.map(coordinates).collect();
+    writer
+        .create_element("LineString")
+        .write_inner_content(|writer| text(writer, "coordinates", &coordinates.join(" ")))?;
+    Ok(())
+}
+
+// KML coordinates are longitude first
+fn coordinates(stop: &NodeLatLon) -> String {
+    let latlon = stop.latlon();
+    format!("{},{}", latlon.lon(), latlon.lat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statue::test_stops;
+
+    #[test]
+    fn writes_placemarks() -> eyre::Result<()> {
+        let stops = test_stops();
+
+        let kml = String::from_utf8(write("Tour", &stops, &[0, 1, 0], Some(&[(0, 1)]))?)?;
+        assert_eq!(kml.matches("<Placemark>").count(), 4);
+        assert!(kml.contains("<name>2. Sinnataggen &amp; co</name>"));
+        assert!(kml
+            .contains("<coordinates>10.7607,59.9127 10.7004,59.927 10.7607,59.9127</coordinates>"));
+
+        Ok(())
+    }
+}
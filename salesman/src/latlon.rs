@@ -1,18 +1,11 @@
-use std::{f32::consts::PI, sync::LazyLock};
+use std::f64::consts::PI;
 
-use crate::edge::Edge;
+use crate::edge::{Cost, Edge, Geographic, Spatial};
 
-const R: f32 = 6371e3; // in metres
+const R: f64 = 6371e3; // in metres
 
-pub const GRONLAND_TBANE: LazyLock<StartingPoint> = LazyLock::new(|| {
-    StartingPoint::new(
-        LatLon::new(59.912_73, 10.761_39),
-        "Grønland T-bane".to_string(),
-    )
-});
-
-pub const KAMPEN: LazyLock<StartingPoint> =
-    LazyLock::new(|| StartingPoint::new(LatLon::new(59.913_34, 10.774_524), "Kampen".to_string()));
+// Latitude the plane projection in `Spatial` is centred on, roughly the middle of Oslo.
+const REFERENCE_LAT: f64 = 59.91;
 
 #[derive(Debug, Clone)]
 pub struct StartingPoint {
@@ -21,34 +14,52 @@ pub struct StartingPoint {
 }
 
 impl StartingPoint {
-    fn new(latlon: LatLon, r#where: String) -> Self {
+    pub fn new(latlon: LatLon, r#where: String) -> Self {
         Self { latlon, r#where }
     }
 
     pub fn latlon(&self) -> &LatLon {
         &self.latlon
     }
+
+    pub fn name(&self) -> &str {
+        &self.r#where
+    }
 }
 
 impl Edge for StartingPoint {
-    fn weight(&self, node: &Self) -> f32 {
+    fn weight(&self, node: &Self) -> Cost {
         self.latlon.weight(&node.latlon)
     }
 }
 
+impl Spatial for StartingPoint {
+    fn position(&self) -> [f64; 2] {
+        self.latlon.position()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LatLon {
-    lat: f32,
-    lon: f32,
+    lat: f64,
+    lon: f64,
 }
 
 impl LatLon {
-    pub fn new(lat: f32, lon: f32) -> Self {
+    pub fn new(lat: f64, lon: f64) -> Self {
         Self { lat, lon }
     }
 
+    pub fn lat(&self) -> f64 {
+        self.lat
+    }
+
+    pub fn lon(&self) -> f64 {
+        self.lon
+    }
+
     // Uses haversine formula to calculate distance in metres.
-    pub fn calculate_distance_to(&self, target: &LatLon) -> f32 {
+    pub fn calculate_distance_to(&self, target: &LatLon) -> f64 {
         let phi_1 = self.lat * (PI / 180.0);
         let phi_2 = target.lat * (PI / 180.0);
 
@@ -60,19 +71,63 @@ impl LatLon {
 
         let c = 2.0 * (a.sqrt().atan2((1.0 - a).sqrt()));
 
-        (R * c).floor() // in metres
+        R * c // in metres
+    }
+
+    // Direction to `target` when setting off, in degrees clockwise from north
+    pub fn bearing_to(&self, target: &LatLon) -> f64 {
+        let phi_1 = self.lat * (PI / 180.0);
+        let phi_2 = target.lat * (PI / 180.0);
+        let delta_lambda = (target.lon - self.lon) * (PI / 180.0);
+
+        let y = delta_lambda.sin() * phi_2.cos();
+        let x = phi_1.cos() * phi_2.sin() - phi_1.sin() * phi_2.cos() * delta_lambda.cos();
+
+        (y.atan2(x) * (180.0 / PI)).rem_euclid(360.0)
+    }
+}
+
+// Closest of the eight compass points to a bearing in degrees
+pub fn compass(bearing: f64) -> &'static str {
+    const POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+
+    POINTS[((bearing.rem_euclid(360.0) + 22.5) / 45.0) as usize % 8]
+}
+
+// Metres below a kilometre, kilometres with one decimal above
+pub fn distance(metres: Cost) -> String {
+    if metres < 1000.0 {
+        format!("{:.0} m", metres)
+    } else {
+        format!("{:.1} km", metres / 1000.0)
     }
 }
 
 impl Edge for LatLon {
-    fn weight(&self, node: &Self) -> f32 {
+    fn weight(&self, node: &Self) -> Cost {
         self.calculate_distance_to(node)
     }
 }
 
+impl Geographic for LatLon {
+    fn latlon(&self) -> LatLon {
+        self.clone()
+    }
+}
+
+// Equirectangular projection to metres, close enough around Oslo to pick neighbour candidates.
+impl Spatial for LatLon {
+    fn position(&self) -> [f64; 2] {
+        let x = R * self.lon * (PI / 180.0) * (REFERENCE_LAT * (PI / 180.0)).cos();
+        let y = R * self.lat * (PI / 180.0);
+
+        [x, y]
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::LatLon;
+    use super::{compass, LatLon};
 
     #[test]
     fn test() {
@@ -81,6 +136,23 @@ mod tests {
 
         let distance = point_1.calculate_distance_to(&point_2);
 
-        assert_eq!(distance, 8239.0)
+        assert!((distance - 8239.427).abs() < 0.001, "{}", distance)
+    }
+
+    #[test]
+    fn bearings() {
+        let oslo = LatLon::new(59.91, 10.75);
+
+        let north = oslo.bearing_to(&LatLon::new(60.0, 10.75));
+        assert!(north.abs() < 1e-9, "{}", north);
+
+        // Due east sets off slightly north of east, along the great circle
+        let east = oslo.bearing_to(&LatLon::new(59.91, 10.85));
+        assert!((89.9..90.0).contains(&east), "{}", east);
+
+        let south_west = oslo.bearing_to(&LatLon::new(59.85, 10.63));
+        assert_eq!(compass(south_west), "SW");
+        assert_eq!(compass(359.0), "N");
+        assert_eq!(compass(-90.0), "W");
     }
 }
@@ -1,104 +1,896 @@
-use std::{cmp::Ordering, env, ops::Deref};
+use std::{cmp::Ordering, env, fmt::Debug, path::Path, str::FromStr};
 
-use edge::NodeLatLon;
-use latlon::{LatLon, GRONLAND_TBANE};
+use compare::{compare, PIPELINES, RUNS};
+use config::{take_flag, Command, Config, Format, Profile};
+use cycling::Docks;
+use edge::{Cost, Edge, Geographic, NodeLatLon, Spatial};
+use eyre::eyre;
+use latlon::{compass, distance, LatLon};
+use matrix::DistanceMatrix;
+use metric::DistanceMetric;
 use mst::MST;
-use statue::{MaybeStatue, Statue};
+use pipeline::{PipelineConfig, Stage};
+use places::StartingPoints;
+use report::{Leg, Report, Stop, Timing};
+use statue::Statue;
+use streets::{Mode, StreetNetwork};
+use transit::{parse_time, today, Gtfs};
+use travel::{Elevation, TravelTime};
 use tsp::TSP;
+use tsp_ext::{candidates::Candidates, local_search::Strategy, solver::Problem};
+use validate::validate;
 
+mod compare;
+mod config;
+mod csv_export;
+mod cycling;
 mod edge;
+mod geojson;
+mod gpx;
+mod html;
+mod kdtree;
+mod kml;
 mod latlon;
+mod matrix;
+mod metric;
 mod mst;
+mod pbf;
+mod pipeline;
+mod places;
+mod precomputed;
+mod render;
+mod report;
+mod sources;
 mod statue;
+mod streets;
+mod tour;
+mod transit;
+mod travel;
 mod tsp;
 mod tsp_ext;
+mod tsplib;
+mod validate;
+
+// Number of nearest neighbours the solvers consider for every node
+const CANDIDATES: usize = 10;
+
+// Read from the working directory if no other config file is given
+const CONFIG: &str = "salesman.toml";
+
+// Shown when there is nothing to plan a route through
+const USAGE: &str =
+    "Usage: salesman [compare | validate] <statues> [matrix cache] [OSM extract] [flags]
+
+The statues are a CSV, GeoJSON, JSON or OSM file, or a TSPLIB .tsp instance to solve as it is.
+Every setting, the statues included, can also come from salesman.toml or a --profile in it.";
+
+// Number of statues closest to the start that are visited if nothing else is given
+const LIMIT: usize = 25;
+
+// Where the tour starts if nothing else is given
+const START: &str = "Grønland T-bane";
+
+// When the tour leaves the starting point if nothing else is given, in time for the T-bane
+const DEPARTURE: &str = "10:00";
+
+// NOTE: Everything picked on the command line or in the profile that decides how a tour is
+// costed, solved and printed.
+struct Options {
+    cache: Option<String>,
+    // Minimise minutes instead of metres
+    travel: Option<TravelTime>,
+    elevation: Option<Elevation>,
+    gtfs: Option<Gtfs>,
+    // Seconds since midnight
+    departure: u32,
+    docks: Option<Docks>,
+    network: Option<StreetNetwork>,
+    // Streets the bikes of a bike share are ridden through, `network` is walked between the docks
+    bike_network: Option<StreetNetwork>,
+    metric: DistanceMetric,
+    // The last node is where the tour ends instead of going back to the start
+    open: bool,
+    pipeline: PipelineConfig,
+    strategy: Strategy,
+    // Drawn at random if none is given, so that the run can be repeated from its report
+    seed: u64,
+    format: Format,
+    // Compare `pipelines` over `seeds` instead of running `pipeline` once
+    compare: bool,
+    pipelines: Vec<PipelineConfig>,
+    seeds: Vec<u64>,
+    // Where the instance is exported to in TSPLIB format
+    tsplib: Option<String>,
+}
+
+// NOTE: What solving found: the order of the stops, and the edges of the minimum spanning tree the
+// lower bound comes from, both by index in the path. Costs are in whatever `quantity` measures.
+struct Solution {
+    order: Vec<usize>,
+    tree: Vec<(usize, usize)>,
+    quantity: &'static str,
+    // Empty when pipelines were compared
+    stages: Vec<Stage>,
+    cost: Cost,
+    mst: Cost,
+    lower_bound: Cost,
+    // Cost of every leg from one stop in `order` to the next
+    legs: Vec<Cost>,
+    // Metres of every leg, if they are not the distance between the stops, e.g. partly cycled
+    distances: Option<Vec<Cost>>,
+}
 
 fn main() -> eyre::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        panic!("Send in path to file plis");
+    let mut args: Vec<String> = env::args().collect();
+
+    // Settings on the command line win over the picked profile, which wins over the defaults of
+    // the config file. The config is read from salesman.toml if `--config` does not point elsewhere.
+    let config = take_flag(&mut args, "--config")?;
+    let profile_name = take_flag(&mut args, "--profile")?;
+
+    let (command, cli) = Profile::from_args(&mut args)?;
+    let compare = command == Command::Compare;
+    let validate = command == Command::Validate;
+
+    let config = match config {
+        Some(path) => Some(Config::load(path)?),
+        None if Path::new(CONFIG).exists() => Some(Config::load(CONFIG)?),
+        None => None,
+    };
+    let profile = match (&config, profile_name) {
+        (Some(config), name) => cli.or(config.profile(name.as_deref())?),
+        (None, Some(name)) => return Err(eyre!("No {} to find profile {} in", CONFIG, name)),
+        (None, None) => cli,
+    };
+
+    let Some(input) = profile.input.clone() else {
+        return Err(eyre!("No statues to plan a route through\n\n{}", USAGE));
+    };
+
+    if validate {
+        return print_validation(&input, parse_or_default(&profile.output)?);
     }
 
-    let path = args[1].clone();
+    // Straight-line distances are measured with haversine unless the metric says otherwise
+    let metric: DistanceMetric = parse_or_default(&profile.metric)?;
 
-    let mut statues: Vec<Statue> = get_from_path(path)?
+    // Cycling rides instead of walking, between the docks of a bike share if there are any
+    let docks = match &profile.docks {
+        Some(path) => Some(Docks::load(path)?),
+        None => None,
+    };
+    let mode: Mode = parse_or_default(&profile.mode)?;
+    let mode = if docks.is_some() { Mode::Cycling } else { mode };
+    // With a bike share, only the legs between the docks are cycled and the rest is walked
+    let walked = if docks.is_some() { Mode::Walking } else { mode };
+
+    // Minutes are minimised instead of metres if asked for, or implied by any of the travel settings
+    let mut travel = match mode {
+        Mode::Walking => TravelTime::default(),
+        Mode::Cycling => TravelTime::cycling(),
+    };
+    if let Some(speed) = profile.speed {
+        travel = travel.with_speed(speed);
+    }
+    if let Some(climb) = profile.climb {
+        travel = travel.with_climb_penalty(climb);
+    }
+    if let Some(dwell) = profile.dwell {
+        travel = travel.with_dwell(dwell);
+    }
+    let elevation = match &profile.elevation {
+        Some(path) => Some(Elevation::load(path)?),
+        None => None,
+    };
+
+    // Legs can take public transport from a GTFS feed, leaving at `departure` on `date` or today.
+    // The timetable decides how long legs take, which leaves nothing for an elevation model.
+    let departure = parse_time(profile.departure.as_deref().unwrap_or(DEPARTURE))?;
+    let gtfs = match &profile.gtfs {
+        Some(_) if elevation.is_some() => {
+            return Err(eyre!(
+                "An elevation model can't be combined with a GTFS feed"
+            ));
+        }
+        Some(path) => Some(Gtfs::load(path, profile.date.unwrap_or_else(today))?),
+        None => None,
+    };
+
+    let timed = match profile.cost.as_deref() {
+        Some("time") => true,
+        Some("distance") => false,
+        Some(cost) => return Err(eyre!("Unknown cost {}, expected distance or time", cost)),
+        None => {
+            mode == Mode::Cycling
+                || profile.speed.is_some()
+                || profile.climb.is_some()
+                || profile.dwell.is_some()
+                || elevation.is_some()
+                || gtfs.is_some()
+        }
+    };
+
+    // Start and end are picked by name, `lat,lon` or `nearest:lat,lon`, from the built-in
+    // starting points or the CSV in `places`. Without an end the tour is a round trip.
+    let places = match &profile.places {
+        Some(path) => StartingPoints::load(path)?,
+        None => StartingPoints::default(),
+    };
+    let start = places.find(&profile.start.as_deref().unwrap_or(START).parse()?)?;
+    let end = match &profile.end {
+        Some(end) => Some(places.find(&end.parse()?)?),
+        None => None,
+    };
+
+    let network = match &profile.streets {
+        Some(streets) => Some(StreetNetwork::load(streets, walked)?),
+        None => None,
+    };
+    let bike_network = match (&profile.streets, &docks) {
+        (Some(streets), Some(_)) => Some(StreetNetwork::load(streets, Mode::Cycling)?),
+        _ => None,
+    };
+
+    let options = Options {
+        cache: profile.matrix.clone(),
+        travel: timed.then_some(travel),
+        elevation,
+        gtfs,
+        departure,
+        docks,
+        network,
+        bike_network,
+        metric,
+        open: end.is_some(),
+        pipeline: parse_or_default(&profile.pipeline)?,
+        strategy: match profile.strategy.as_deref() {
+            None | Some("first") => Strategy::FirstImprovement,
+            Some("best") => Strategy::BestImprovement,
+            Some(strategy) => {
+                return Err(eyre!(
+                    "Unknown strategy {}, expected first or best",
+                    strategy
+                ))
+            }
+        },
+        seed: profile.seed.unwrap_or_else(rand::random),
+        format: parse_or_default(&profile.output)?,
+        compare,
+        pipelines: match &profile.pipelines {
+            Some(pipelines) => pipelines
+                .iter()
+                .map(|pipeline| pipeline.parse())
+                .collect::<eyre::Result<_>>()?,
+            None => PIPELINES
+                .iter()
+                .map(|pipeline| pipeline.parse())
+                .collect::<eyre::Result<_>>()?,
+        },
+        seeds: {
+            let seed = profile.seed.unwrap_or(0);
+            (0..profile.runs.unwrap_or(RUNS))
+                .map(|run| seed.wrapping_add(run))
+                .collect()
+        },
+        tsplib: profile.tsplib.clone(),
+    };
+
+    // TSPLIB instances come with their own weights, none of the statue settings apply to them
+    if input.ends_with(".tsp") {
+        return solve_tsplib(&input, &options, &profile);
+    }
+
+    let search = profile.search.as_ref().map(|search| search.to_lowercase());
+    let mut statues: Vec<Statue> = sources::load(input)?
+        .filled()?
         .into_iter()
         .filter_map(|val| val.try_into().ok())
+        .filter(|statue: &Statue| match &search {
+            Some(search) => statue.matches(search),
+            None => true,
+        })
+        .filter(|statue| match profile.within {
+            Some(within) => metric.distance(&statue.latlon(), start.latlon()) <= within,
+            None => true,
+        })
         .collect();
 
-    let start = GRONLAND_TBANE.deref().clone();
-
     // Sort statues by proximity to start point for testing to something close to home<3
     statues.sort_by(|a: &Statue, b: &Statue| -> Ordering {
         let a_pos: LatLon = a.latlon();
         let b_pos: LatLon = b.latlon();
 
-        let diff = a_pos.calculate_distance_to(start.latlon())
-            - b_pos.calculate_distance_to(start.latlon());
+        let diff =
+            metric.distance(&a_pos, start.latlon()) - metric.distance(&b_pos, start.latlon());
 
         if diff == 0.0 {
             Ordering::Equal
         } else if diff < 0.0 {
-            return Ordering::Less;
+            Ordering::Less
         } else {
-            return Ordering::Greater;
+            Ordering::Greater
         }
     });
 
+    match profile.limit.unwrap_or(LIMIT) {
+        0 => {}
+        limit => statues.truncate(limit),
+    }
+
     let mut path: Vec<NodeLatLon> = vec![NodeLatLon::StartingPoint(start)];
 
     path.append(
-        &mut statues[0..25]
+        &mut statues
             .iter()
             .map(|s| NodeLatLon::Statue(s.clone()))
             .collect::<Vec<NodeLatLon>>(),
     );
+    path.extend(end.map(NodeLatLon::StartingPoint));
+
+    let names: Vec<String> = path.iter().map(|node| node.name().to_string()).collect();
+    let stops = path.clone();
+
+    let solution = match &options.network {
+        Some(network) => run(network.route(path), &names, &options)?,
+        None => run(metric.measure(path), &names, &options)?,
+    };
+    let order = solution.order.clone();
+
+    let title = format!("Statues from {}", names[0]);
+    // The spanning tree is only written when asked for, it is there to debug the lower bound
+    let tree = profile
+        .mst
+        .unwrap_or(false)
+        .then_some(solution.tree.as_slice());
+    if let Some(file) = &profile.gpx {
+        gpx::save(file, &title, &stops, &order)?;
+    }
+    if let Some(file) = &profile.geojson {
+        geojson::save(file, &stops, &order, tree)?;
+    }
+    if let Some(file) = &profile.kml {
+        kml::save(file, &title, &stops, &order, tree)?;
+    }
+    if let Some(file) = &profile.render {
+        render::save(file, &stops, &order, tree)?;
+    }
+    // Only measured when needed, through a street network it takes a shortest path search per stop
+    let needs_distances =
+        profile.html.is_some() || profile.csv.is_some() || options.format != Format::Names;
+    let distances = if needs_distances {
+        match &solution.distances {
+            Some(distances) => distances.clone(),
+            None => leg_distances(&stops, &order, &options),
+        }
+    } else {
+        vec![]
+    };
+
+    if let Some(file) = &profile.html {
+        html::save(file, &title, &stops, &order, &distances)?;
+    }
+    if let Some(file) = &profile.csv {
+        // Times of the day when leaving at a known departure for public transport, like the
+        // itinerary, otherwise the time since setting off
+        let etas: Option<Vec<String>> = options.travel.map(|travel| {
+            let start = match options.gtfs {
+                Some(_) => options.departure as Cost / 60.0,
+                None => 0.0,
+            };
+            let positions: Vec<usize> = (0..order.len()).collect();
+            travel
+                .schedule(&positions, |p, _, _| solution.legs[p])
+                .iter()
+                .map(|stop| clock(start + stop.arrival))
+                .collect()
+        });
+
+        csv_export::save(file, &stops, &order, &distances, etas.as_deref())?;
+    }
+
+    match options.format {
+        Format::Text => {
+            // Minutes come from the cost model if it is timed, otherwise from walking the distance
+            let minutes: Vec<Cost> = match options.travel {
+                Some(_) => solution.legs.clone(),
+                None => distances
+                    .iter()
+                    .map(|metres| TravelTime::default().minutes(*metres, 0.0))
+                    .collect(),
+            };
+            print_route(&stops, &order, &distances, &minutes);
+        }
+        Format::Names => {
+            for i in order {
+                println!("{}", names[i]);
+            }
+        }
+        Format::Json => {
+            let stops = order.iter().map(|i| Stop::from(&stops[*i])).collect();
+            print_json(&profile, &options, &solution, stops, Some(&distances))?;
+        }
+    }
+
+    Ok(())
+}
+
+// Parses the setting if it is there, or gives the default
+fn parse_or_default<T>(value: &Option<String>) -> eyre::Result<T>
+where
+    T: FromStr<Err = eyre::Error> + Default,
+{
+    match value {
+        Some(value) => value.parse(),
+        None => Ok(T::default()),
+    }
+}
+
+// Solves the tour and prints the itinerary if it is timed, returning the order of the stops
+// and the spanning tree
+fn run<E: Edge + Geographic + Spatial + Clone + Debug>(
+    path: Vec<E>,
+    names: &[String],
+    options: &Options,
+) -> eyre::Result<Solution> {
+    let Some(travel) = options.travel else {
+        return solve(&path, options, "distance");
+    };
+    let text = options.format == Format::Text;
+
+    // The timetable decides how long legs take with public transport
+    if let Some(gtfs) = &options.gtfs {
+        let transit = gtfs.transit(path.clone(), options.departure, travel);
+        let mut solution = solve(&transit, options, "time")?;
+
+        // Solved for leaving at the departure, then every leg is timed for when the tour gets there
+        let legs = gtfs.legs(&path, &solution.order, options.departure, travel);
+        solution.legs = legs.iter().map(|(minutes, _)| *minutes).collect();
+        solution.cost = solution.legs.iter().sum();
+
+        // The departure is known, so show times of the day
+        let start = options.departure as Cost / 60.0;
+        if text {
+            print_itinerary(
+                &travel,
+                &solution.order,
+                &solution.legs,
+                names,
+                start,
+                |p| legs[p].1.then(|| "transit".to_string()),
+            );
+        }
+
+        return Ok(solution);
+    }
+
+    // Bike share legs are measured the same way as the nodes, walked through the streets and
+    // cycled through the roads a bike may use if there is a network
+    if let Some(docks) = &options.docks {
+        let walking = TravelTime::default();
+        let (path, rides) = docks.ride(
+            path,
+            walking,
+            travel,
+            options.elevation.as_ref(),
+            |places| measure(places, options.network.as_ref(), options.metric),
+            |places| measure(places, options.bike_network.as_ref(), options.metric),
+        );
+        let mut solution = solve(&path, options, "time")?;
+        solution.distances = Some(
+            solution
+                .order
+                .windows(2)
+                .map(|pair| rides.metres(pair[0], pair[1]))
+                .collect(),
+        );
+
+        if text {
+            let order = &solution.order;
+            print_itinerary(&travel, order, &solution.legs, names, 0.0, |p| {
+                rides
+                    .docks(order[p], order[p + 1])
+                    .map(|(from, to)| format!("bike from {} to {}", from, to))
+            });
+        }
+
+        return Ok(solution);
+    }
+
+    let path = travel.timed(path, options.elevation.as_ref());
+    let solution = solve(&path, options, "time")?;
+    if text {
+        print_itinerary(&travel, &solution.order, &solution.legs, names, 0.0, |_| {
+            None
+        });
+    }
 
-    let mut mst = MST::new(path.clone());
+    Ok(solution)
+}
+
+// Length in metres of every leg from one stop in `order` to the next
+fn leg_distances(stops: &[NodeLatLon], order: &[usize], options: &Options) -> Vec<Cost> {
+    let places: Vec<LatLon> = stops.iter().map(|stop| stop.latlon()).collect();
+    let distances = measure(&places, options.network.as_ref(), options.metric);
+
+    order
+        .windows(2)
+        .map(|pair| distances[pair[0] * places.len() + pair[1]])
+        .collect()
+}
+
+// Every leg on a line of its own, e.g.
+// `1. Grønland T-bane → 2. Sinnataggen (420 m NW, ~5 min, total 0.4 km)`, followed by the address
+// of where it leads if it is a statue.
+fn print_route(stops: &[NodeLatLon], order: &[usize], distances: &[Cost], minutes: &[Cost]) {
+    println!("Route:");
+
+    // A round trip ends where it started, which keeps its number
+    let visits = tour::visits(order).len();
+    let number = |p: usize| if p < visits { p + 1 } else { 1 };
+
+    let mut total = 0.0;
+    for (p, pair) in order.windows(2).enumerate() {
+        let (from, to) = (&stops[pair[0]], &stops[pair[1]]);
+        total += distances[p];
+
+        println!(
+            "{}. {} → {}. {} ({} {}, ~{:.0} min, total {:.1} km)",
+            number(p),
+            from.name(),
+            number(p + 1),
+            to.name(),
+            distance(distances[p]),
+            compass(from.latlon().bearing_to(&to.latlon())),
+            minutes[p],
+            total / 1000.0
+        );
+        if let NodeLatLon::Statue(statue) = to {
+            println!("   {}", statue.address());
+        }
+    }
+}
+
+// Distances between every pair of places, row by row, through the network if there is one
+fn measure(
+    places: &[LatLon],
+    network: Option<&StreetNetwork>,
+    metric: DistanceMetric,
+) -> Vec<Cost> {
+    match network {
+        Some(network) => {
+            let positions: Vec<[f64; 2]> = places.iter().map(|place| place.position()).collect();
+            network.distances(&positions)
+        }
+        None => places
+            .iter()
+            .flat_map(|a| places.iter().map(|b| metric.distance(a, b)))
+            .collect(),
+    }
+}
+
+// Arrival and departure at every stop, `start` minutes after midnight. `legs` are the minutes from
+// one stop in `order` to the next, and `leg` can describe how the leg at a position is travelled.
+fn print_itinerary(
+    travel: &TravelTime,
+    order: &[usize],
+    legs: &[Cost],
+    names: &[String],
+    start: Cost,
+    leg: impl Fn(usize) -> Option<String>,
+) {
+    println!("Itinerary:");
+
+    let positions: Vec<usize> = (0..order.len()).collect();
+    let stops = travel.schedule(&positions, |p, _, _| legs[p]);
+    for (p, stop) in stops.iter().enumerate() {
+        let by = match p {
+            0 => None,
+            _ => leg(p - 1),
+        };
+
+        println!(
+            "{} - {}  {}{}",
+            clock(start + stop.arrival),
+            clock(start + stop.departure),
+            names[order[p]],
+            by.map(|by| format!(" ({})", by)).unwrap_or_default()
+        );
+    }
+}
+
+// Runs the solvers and returns the order of the best tour, ending at the last node instead of the
+// first if the tour is open.
+fn solve<E: Edge + Spatial + Clone + Debug>(
+    path: &[E],
+    options: &Options,
+    quantity: &'static str,
+) -> eyre::Result<Solution> {
+    if !options.open {
+        return solve_tour(path, options, quantity);
+    }
+
+    let mut solution = solve_tour(&places::open(path.to_vec()), options, quantity)?;
+    // The first node stands in for the last one on the way back
+    if let Some(last) = solution.order.last_mut() {
+        *last = path.len() - 1;
+    }
+
+    Ok(solution)
+}
+
+// Runs the pipeline and returns the order of the tour along with the spanning tree. `quantity`
+// names what the cost measures.
+fn solve_tour<E: Edge + Spatial + Clone + Debug>(
+    path: &[E],
+    options: &Options,
+    quantity: &'static str,
+) -> eyre::Result<Solution> {
+    // Calculated once and shared by the solvers, reused between runs if a cache file is given
+    let matrix = match &options.cache {
+        Some(cache) => load_or_calculate_matrix(cache, path)?,
+        None => DistanceMatrix::dense(path),
+    };
+
+    let mut mst = MST::new(path.to_vec()).with_matrix(matrix.clone());
     mst.solve();
-    let mst_lower_bound = mst.calculate_cost();
 
-    let mut tsp = TSP::new(path.clone());
-    // let mut tsp = TSP::new_and_initialize_path(statues[0..20].to_vec());
-    tsp.nn();
-    tsp.three_opt();
+    if options.compare {
+        let candidates = Candidates::new(path, CANDIDATES);
+        let comparison = compare(
+            path,
+            &matrix,
+            &candidates,
+            options.strategy,
+            &options.pipelines,
+            &options.seeds,
+        );
+        if options.format == Format::Text {
+            comparison.print(quantity, mst.calculate_one_tree_cost());
+        }
+
+        let order = comparison.best.closed_from(0);
+        export_tsplib(path, &matrix, &order, options, quantity)?;
 
-    // println!("Path:\n{:#?}", tsp.path());
-    let tsp_cost = tsp.calculate_path_cost();
-    // println!("Total distance three-opt: {}", tsp_cost);
+        let problem = Problem::new(path, &matrix);
+        return Ok(Solution {
+            legs: order
+                .windows(2)
+                .map(|pair| problem.weight(pair[0], pair[1]))
+                .collect(),
+            cost: problem.cost(&comparison.best),
+            order,
+            tree: mst.edges(),
+            quantity,
+            stages: vec![],
+            mst: mst.calculate_cost(),
+            lower_bound: mst.calculate_one_tree_cost(),
+            distances: None,
+        });
+    }
+
+    let mut tsp = TSP::new(path.to_vec())
+        .with_candidates(CANDIDATES)
+        .with_strategy(options.strategy)
+        .with_matrix(matrix.clone())
+        .with_seed(options.seed);
+    let stages = tsp.run(&options.pipeline.build());
 
-    // println!("MST lower bound: {}", mst_lower_bound);
-    // println!(
-    //     "Calculated distance to lower bound ratio: {}",
-    //     tsp_cost / mst_lower_bound
-    // );
+    if options.format == Format::Text {
+        for stage in stages.iter() {
+            println!("{}: {} ({:.2?})", stage.name, stage.cost, stage.elapsed);
+        }
 
-    let mut tsp = TSP::new(path);
+        let mst_lower_bound = mst.calculate_cost();
 
-    tsp.nn();
-    tsp.three_opt_sm();
+        let tsp_cost = tsp.calculate_path_cost();
+        println!("Total {} {}: {}", quantity, options.pipeline, tsp_cost);
 
-    println!("Path:\n{:#?}", tsp.path());
-    let tsp_cost = tsp.calculate_path_cost();
-    println!("Total distance sm: {}", tsp_cost);
+        println!("MST lower bound: {}", mst_lower_bound);
+        println!(
+            "Calculated {} to lower bound ratio: {}",
+            quantity,
+            tsp_cost / mst_lower_bound
+        );
+        println!("1-tree lower bound: {}", mst.calculate_one_tree_cost());
+    }
 
-    // println!("MST lower bound: {}", mst_lower_bound);
-    // println!(
-    //     "Calculated distance to lower bound ratio: {}",
-    //     tsp_cost / mst_lower_bound
-    // );
+    let order = tsp.order();
+    export_tsplib(path, &matrix, &order, options, quantity)?;
+
+    Ok(Solution {
+        legs: tsp.legs(),
+        cost: tsp.calculate_path_cost(),
+        order,
+        tree: mst.edges(),
+        quantity,
+        stages,
+        mst: mst.calculate_cost(),
+        lower_bound: mst.calculate_one_tree_cost(),
+        distances: None,
+    })
+}
+
+// Prints the report of the run as JSON. `distances` are the lengths of the legs in metres, if the
+// stops are on a map.
+fn print_json(
+    profile: &Profile,
+    options: &Options,
+    solution: &Solution,
+    stops: Vec<Stop>,
+    distances: Option<&[Cost]>,
+) -> eyre::Result<()> {
+    let report = Report {
+        parameters: profile,
+        pipeline: options.pipeline.to_string(),
+        // Compared pipelines ran with the seeds counting up from the first one
+        seed: match options.compare {
+            true => options.seeds.first().copied(),
+            false => Some(options.seed),
+        },
+        quantity: solution.quantity,
+        cost: solution.cost,
+        mst: solution.mst,
+        lower_bound: solution.lower_bound,
+        stages: solution.stages.iter().map(Timing::from).collect(),
+        stops,
+        legs: solution
+            .legs
+            .iter()
+            .enumerate()
+            .map(|(i, cost)| Leg {
+                cost: *cost,
+                distance: distances.map(|distances| distances[i]),
+            })
+            .collect(),
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    Ok(())
+}
+
+// Writes the instance and its tour in TSPLIB format if asked to, with times in whole seconds
+fn export_tsplib<E: Edge + Spatial>(
+    path: &[E],
+    matrix: &DistanceMatrix,
+    order: &[usize],
+    options: &Options,
+    quantity: &str,
+) -> eyre::Result<()> {
+    let Some(file) = &options.tsplib else {
+        return Ok(());
+    };
+
+    let name = Path::new(file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let (comment, scale) = match quantity {
+        "time" => ("Time in seconds", 60.0),
+        _ => ("Distance in metres", 1.0),
+    };
+
+    tsplib::save(file, &name, comment, path, matrix, scale)?;
+    tsplib::save_tour(Path::new(file).with_extension("tour"), &name, order)
+}
+
+// Solves a TSPLIB instance, and compares the tour to the optimal one if a .opt.tour file with the
+// same name lies next to it
+fn solve_tsplib(input: &str, options: &Options, profile: &Profile) -> eyre::Result<()> {
+    let instance = tsplib::Instance::load(input)?;
+    let nodes = instance.nodes();
+    let solution = solve_tour(nodes, options, "distance")?;
+    let order = &solution.order;
+
+    match options.format {
+        Format::Text => {
+            let optimal = Path::new(input).with_extension("opt.tour");
+            if optimal.exists() {
+                let mut optimal = tsplib::load_tour(optimal)?;
+                optimal.extend(optimal.first().copied());
+
+                let cost = |order: &[usize]| -> Cost {
+                    order
+                        .windows(2)
+                        .map(|pair| nodes[pair[0]].weight(&nodes[pair[1]]))
+                        .sum()
+                };
+                let optimum = cost(&optimal);
+                println!(
+                    "Gap to the optimal tour of {} ({}): {:.2}%",
+                    instance.name(),
+                    optimum,
+                    (cost(order) / optimum - 1.0) * 100.0
+                );
+            }
+        }
+        Format::Names => {
+            for i in order {
+                println!("{}", nodes[*i].id());
+            }
+        }
+        Format::Json => {
+            let stops = order
+                .iter()
+                .map(|i| Stop {
+                    name: nodes[*i].id().to_string(),
+                    lat: None,
+                    lon: None,
+                    address: None,
+                    link: None,
+                })
+                .collect();
+            print_json(profile, options, &solution, stops, None)?;
+        }
+    }
 
     Ok(())
 }
 
-fn get_from_path(path: String) -> eyre::Result<Vec<MaybeStatue>> {
-    let mut rdr = csv::Reader::from_path(path)?;
-    let mut output = vec![];
+// Rows that are dropped when planning and why, then everything that looks off about the rest
+fn print_validation(input: &str, format: Format) -> eyre::Result<()> {
+    let loaded = sources::load(input)?;
+    let rows = loaded.rows.len();
+    let findings = validate(&loaded);
+    let (rejected, warnings): (Vec<_>, Vec<_>) = findings
+        .into_iter()
+        .partition(|finding| finding.issue.rejects());
 
-    for record in rdr.deserialize() {
-        let record: MaybeStatue = record?;
-        output.push(record);
+    if format == Format::Json {
+        let value = serde_json::json!({
+            "input": input,
+            "rows": rows,
+            "header": loaded.header,
+            "usable": rows - rejected.len(),
+            "rejected": rejected,
+            "warnings": warnings,
+        });
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} rows in {}, {} usable, {} rejected, {} warnings",
+        rows,
+        input,
+        rows - rejected.len(),
+        rejected.len(),
+        warnings.len()
+    );
+    if loaded.header {
+        println!("Rows are counted from the line after the header");
     }
+    for (heading, findings) in [("Rejected", &rejected), ("Warnings", &warnings)] {
+        if findings.is_empty() {
+            continue;
+        }
+        println!("{}:", heading);
+        for finding in findings {
+            match finding.title.is_empty() {
+                true => println!("  row {}: {}", finding.row, finding.issue),
+                false => println!("  row {} {}: {}", finding.row, finding.title, finding.issue),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Minutes as hours and minutes, e.g. 1:05
+fn clock(minutes: Cost) -> String {
+    let minutes = minutes.round() as u64;
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+fn load_or_calculate_matrix<E: Edge>(cache: &str, nodes: &[E]) -> eyre::Result<DistanceMatrix> {
+    if Path::new(cache).exists() {
+        match DistanceMatrix::load(cache, nodes) {
+            Ok(matrix) => return Ok(matrix),
+            Err(err) => eprintln!("Calculating distance matrix again: {}", err),
+        }
+    }
+
+    let matrix = DistanceMatrix::dense(nodes);
+    matrix.save(cache)?;
 
-    Ok(output)
+    Ok(matrix)
 }
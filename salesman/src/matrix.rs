@@ -0,0 +1,177 @@
+use std::{
+    cell::Cell,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use eyre::eyre;
+
+use crate::edge::{Cost, Edge};
+
+// Written at the start of a persisted matrix, followed by the size and the cells.
+const MAGIC: &[u8; 8] = b"SLSMTX02";
+
+// Cache of edge weights between nodes, addressed by node index.
+// A lazy matrix starts out empty and fills a cell the first time it is asked for,
+// so solvers restricted to candidates never pay for the weights they do not look at.
+#[derive(Debug, Clone)]
+pub struct DistanceMatrix {
+    size: usize,
+    // Row-major, NaN marks a cell that has not been calculated yet
+    cells: Vec<Cell<Cost>>,
+}
+
+impl DistanceMatrix {
+    pub fn dense<E: Edge>(nodes: &[E]) -> Self {
+        let matrix = Self::lazy(nodes.len());
+        matrix.fill(nodes);
+        matrix
+    }
+
+    pub fn lazy(size: usize) -> Self {
+        Self {
+            size,
+            cells: vec![Cell::new(Cost::NAN); size * size],
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get<E: Edge>(&self, nodes: &[E], i: usize, j: usize) -> Cost {
+        let cell = &self.cells[i * self.size + j];
+        let weight = cell.get();
+
+        if !weight.is_nan() {
+            return weight;
+        }
+
+        let weight = nodes[i].weight(&nodes[j]);
+        cell.set(weight);
+        weight
+    }
+
+    // Calculates every cell that is still missing
+    pub fn fill<E: Edge>(&self, nodes: &[E]) {
+        for i in 0..self.size {
+            for j in 0..self.size {
+                self.get(nodes, i, j);
+            }
+        }
+    }
+
+    // Writes the matrix as it is, cells that have not been calculated yet are stored as missing.
+    pub fn save(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(self.size as u64).to_le_bytes())?;
+
+        for cell in self.cells.iter() {
+            writer.write_all(&cell.get().to_le_bytes())?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    // Reads a matrix written by `save` and checks that it was calculated for these nodes,
+    // by comparing the stored weights from the first node against freshly calculated ones.
+    pub fn load<E: Edge>(path: impl AsRef<Path>, nodes: &[E]) -> eyre::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(eyre!("Not a distance matrix file"));
+        }
+
+        let mut size = [0; 8];
+        reader.read_exact(&mut size)?;
+        let size = u64::from_le_bytes(size) as usize;
+
+        if size != nodes.len() {
+            return Err(eyre!(
+                "Distance matrix is for {} nodes, but there are {}",
+                size,
+                nodes.len()
+            ));
+        }
+
+        let mut cells = Vec::with_capacity(size * size);
+        let mut buf = [0; 8];
+        for _ in 0..(size * size) {
+            reader.read_exact(&mut buf)?;
+            cells.push(Cell::new(Cost::from_le_bytes(buf)));
+        }
+
+        let matrix = Self { size, cells };
+
+        for (j, node) in nodes.iter().enumerate() {
+            let stored = matrix.cells[j].get();
+            if !stored.is_nan() && stored != nodes[0].weight(node) {
+                return Err(eyre!("Distance matrix does not match the nodes"));
+            }
+        }
+
+        Ok(matrix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone)]
+    struct OneDPoint {
+        x: f64,
+    }
+
+    impl Edge for OneDPoint {
+        fn weight(&self, node: &Self) -> Cost {
+            (self.x - node.x).abs()
+        }
+    }
+
+    fn nodes() -> Vec<OneDPoint> {
+        [0.0, 3.0, 4.0, 10.0]
+            .into_iter()
+            .map(|x| OneDPoint { x })
+            .collect()
+    }
+
+    #[test]
+    fn lazy_fills_on_demand() {
+        let nodes = nodes();
+        let matrix = DistanceMatrix::lazy(nodes.len());
+
+        assert!(matrix.cells[1].get().is_nan());
+        assert_eq!(matrix.get(&nodes, 0, 1), 3.0);
+        assert_eq!(matrix.cells[1].get(), 3.0);
+        assert!(matrix.cells[2].get().is_nan());
+    }
+
+    #[test]
+    fn save_and_load() -> eyre::Result<()> {
+        let nodes = nodes();
+        let matrix = DistanceMatrix::dense(&nodes);
+
+        let path = std::env::temp_dir().join(format!("salesman-matrix-{}", std::process::id()));
+        matrix.save(&path)?;
+
+        let loaded = DistanceMatrix::load(&path, &nodes)?;
+        assert_eq!(loaded.get(&nodes, 2, 3), 6.0);
+
+        assert!(DistanceMatrix::load(&path, &nodes[0..3]).is_err());
+
+        let other: Vec<OneDPoint> = nodes.iter().map(|n| OneDPoint { x: n.x * 2.0 }).collect();
+        assert!(DistanceMatrix::load(&path, &other).is_err());
+
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+use std::{
+    fmt::{self, Debug},
+    str::FromStr,
+};
+
+use eyre::eyre;
+
+use crate::{
+    edge::{Cost, Edge, Geographic, Spatial},
+    latlon::LatLon,
+};
+
+// WGS84 ellipsoid
+const A: f64 = 6378137.0;
+const F: f64 = 1.0 / 298.257223563;
+
+// Mean earth radius, same as the one haversine uses
+const R: f64 = 6371e3;
+
+// UTM zone 32N, which covers Oslo
+const UTM_K0: f64 = 0.9996;
+const UTM_CENTRAL_MERIDIAN: f64 = 9.0;
+const UTM_FALSE_EASTING: f64 = 500000.0;
+
+// How the straight-line distance between two coordinates is measured.
+// Vincenty is the most accurate, equirectangular the cheapest to calculate, and UTM measures in
+// the plane the official Norwegian maps use. All of them are within a few metres of each other
+// inside Oslo. Distances are measured whenever they are asked for, so the metric decides how
+// long it takes to fill the solvers' distance matrix.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DistanceMetric {
+    #[default]
+    Haversine,
+    Vincenty,
+    Equirectangular,
+    Utm32N,
+}
+
+impl FromStr for DistanceMetric {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "haversine" => Ok(Self::Haversine),
+            "vincenty" => Ok(Self::Vincenty),
+            "equirectangular" => Ok(Self::Equirectangular),
+            "utm" | "utm32n" => Ok(Self::Utm32N),
+            _ => Err(eyre!(
+                "Unknown distance metric {}, expected haversine, vincenty, equirectangular or utm",
+                s
+            )),
+        }
+    }
+}
+
+impl DistanceMetric {
+    // Distance in metres
+    pub fn distance(&self, from: &LatLon, to: &LatLon) -> Cost {
+        match self {
+            DistanceMetric::Haversine => from.calculate_distance_to(to),
+            DistanceMetric::Vincenty => {
+                vincenty(from, to).unwrap_or_else(|| from.calculate_distance_to(to))
+            }
+            DistanceMetric::Equirectangular => equirectangular(from, to),
+            DistanceMetric::Utm32N => {
+                let [x_1, y_1] = utm32n(from);
+                let [x_2, y_2] = utm32n(to);
+
+                ((x_1 - x_2).powi(2) + (y_1 - y_2).powi(2)).sqrt()
+            }
+        }
+    }
+
+    // Wraps the nodes so that their weights are measured with this metric
+    pub fn measure<N: Geographic>(&self, nodes: Vec<N>) -> Vec<Measured<N>> {
+        nodes
+            .into_iter()
+            .map(|node| Measured {
+                node,
+                metric: *self,
+            })
+            .collect()
+    }
+}
+
+// A node whose weight to other nodes is the distance measured with `metric`
+#[derive(Clone)]
+pub struct Measured<N> {
+    node: N,
+    metric: DistanceMetric,
+}
+
+impl<N: Debug> Debug for Measured<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.node.fmt(f)
+    }
+}
+
+impl<N: Geographic> Edge for Measured<N> {
+    fn weight(&self, node: &Self) -> Cost {
+        self.metric
+            .distance(&self.node.latlon(), &node.node.latlon())
+    }
+}
+
+impl<N: Geographic> Geographic for Measured<N> {
+    fn latlon(&self) -> LatLon {
+        self.node.latlon()
+    }
+}
+
+impl<N: Spatial> Spatial for Measured<N> {
+    fn position(&self) -> [f64; 2] {
+        self.node.position()
+    }
+}
+
+// Vincenty's inverse formula on the WGS84 ellipsoid. Does not converge for nearly antipodal
+// points, which never happens inside a city, but gives None instead of looping forever.
+// https://en.wikipedia.org/wiki/Vincenty%27s_formulae
+fn vincenty(from: &LatLon, to: &LatLon) -> Option<f64> {
+    let b = (1.0 - F) * A;
+
+    let l = to.lon().to_radians() - from.lon().to_radians();
+    let u_1 = ((1.0 - F) * from.lat().to_radians().tan()).atan();
+    let u_2 = ((1.0 - F) * to.lat().to_radians().tan()).atan();
+    let (sin_u_1, cos_u_1) = u_1.sin_cos();
+    let (sin_u_2, cos_u_2) = u_2.sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..200 {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+
+        let sin_sigma = ((cos_u_2 * sin_lambda).powi(2)
+            + (cos_u_1 * sin_u_2 - sin_u_1 * cos_u_2 * cos_lambda).powi(2))
+        .sqrt();
+        if sin_sigma == 0.0 {
+            // Same point
+            return Some(0.0);
+        }
+
+        let cos_sigma = sin_u_1 * sin_u_2 + cos_u_1 * cos_u_2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+
+        let sin_alpha = cos_u_1 * cos_u_2 * sin_lambda / sin_sigma;
+        let cos_sq_alpha = 1.0 - sin_alpha * sin_alpha;
+        // Both points on the equator
+        let cos_2_sigma_m = if cos_sq_alpha == 0.0 {
+            0.0
+        } else {
+            cos_sigma - 2.0 * sin_u_1 * sin_u_2 / cos_sq_alpha
+        };
+
+        let c = F / 16.0 * cos_sq_alpha * (4.0 + F * (4.0 - 3.0 * cos_sq_alpha));
+        let previous = lambda;
+        lambda = l
+            + (1.0 - c)
+                * F
+                * sin_alpha
+                * (sigma
+                    + c * sin_sigma
+                        * (cos_2_sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2_sigma_m.powi(2))));
+
+        if (lambda - previous).abs() < 1e-12 {
+            let u_sq = cos_sq_alpha * (A * A - b * b) / (b * b);
+            let big_a =
+                1.0 + u_sq / 16384.0 * (4096.0 + u_sq * (-768.0 + u_sq * (320.0 - 175.0 * u_sq)));
+            let big_b = u_sq / 1024.0 * (256.0 + u_sq * (-128.0 + u_sq * (74.0 - 47.0 * u_sq)));
+
+            let delta_sigma = big_b
+                * sin_sigma
+                * (cos_2_sigma_m
+                    + big_b / 4.0
+                        * (cos_sigma * (-1.0 + 2.0 * cos_2_sigma_m.powi(2))
+                            - big_b / 6.0
+                                * cos_2_sigma_m
+                                * (-3.0 + 4.0 * sin_sigma.powi(2))
+                                * (-3.0 + 4.0 * cos_2_sigma_m.powi(2))));
+
+            return Some(b * big_a * (sigma - delta_sigma));
+        }
+    }
+
+    None
+}
+
+// Flat-earth approximation around the mean latitude of the two points
+fn equirectangular(from: &LatLon, to: &LatLon) -> f64 {
+    let mean_lat = (from.lat().to_radians() + to.lat().to_radians()) / 2.0;
+
+    let x = (to.lon().to_radians() - from.lon().to_radians()) * mean_lat.cos();
+    let y = to.lat().to_radians() - from.lat().to_radians();
+
+    R * (x * x + y * y).sqrt()
+}
+
+// Easting and northing in UTM zone 32N, using the series expansion of the transverse Mercator
+// projection from Snyder's "Map Projections: A Working Manual".
+fn utm32n(latlon: &LatLon) -> [f64; 2] {
+    let e_sq = F * (2.0 - F);
+    let e_prime_sq = e_sq / (1.0 - e_sq);
+
+    let phi = latlon.lat().to_radians();
+    let (sin_phi, cos_phi) = phi.sin_cos();
+
+    let n = A / (1.0 - e_sq * sin_phi * sin_phi).sqrt();
+    let t = phi.tan().powi(2);
+    let c = e_prime_sq * cos_phi * cos_phi;
+    let a = cos_phi * (latlon.lon().to_radians() - UTM_CENTRAL_MERIDIAN.to_radians());
+
+    let m = A
+        * ((1.0 - e_sq / 4.0 - 3.0 * e_sq.powi(2) / 64.0 - 5.0 * e_sq.powi(3) / 256.0) * phi
+            - (3.0 * e_sq / 8.0 + 3.0 * e_sq.powi(2) / 32.0 + 45.0 * e_sq.powi(3) / 1024.0)
+                * (2.0 * phi).sin()
+            + (15.0 * e_sq.powi(2) / 256.0 + 45.0 * e_sq.powi(3) / 1024.0) * (4.0 * phi).sin()
+            - (35.0 * e_sq.powi(3) / 3072.0) * (6.0 * phi).sin());
+
+    let easting = UTM_K0
+        * n
+        * (a + (1.0 - t + c) * a.powi(3) / 6.0
+            + (5.0 - 18.0 * t + t * t + 72.0 * c - 58.0 * e_prime_sq) * a.powi(5) / 120.0)
+        + UTM_FALSE_EASTING;
+
+    let northing = UTM_K0
+        * (m + n
+            * phi.tan()
+            * (a * a / 2.0
+                + (5.0 - t + 9.0 * c + 4.0 * c * c) * a.powi(4) / 24.0
+                + (61.0 - 58.0 * t + t * t + 600.0 * c - 330.0 * e_prime_sq) * a.powi(6) / 720.0));
+
+    [easting, northing]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vincenty_flinders_peak() {
+        // Standard example from Vincenty's paper, 54972.271 m
+        let flinders_peak = LatLon::new(-37.951_033, 144.424_87);
+        let buninyong = LatLon::new(-37.652_82, 143.926_5);
+
+        let distance = DistanceMetric::Vincenty.distance(&flinders_peak, &buninyong);
+        assert!((distance - 54_972.27).abs() < 1.0, "{}", distance);
+    }
+
+    #[test]
+    fn metrics_agree_in_oslo() {
+        let point_1 = LatLon::new(59.875_973, 10.829_73);
+        let point_2 = LatLon::new(59.932_61, 10.734_446);
+
+        let vincenty = DistanceMetric::Vincenty.distance(&point_1, &point_2);
+
+        for metric in [
+            DistanceMetric::Haversine,
+            DistanceMetric::Equirectangular,
+            DistanceMetric::Utm32N,
+        ] {
+            let distance = metric.distance(&point_1, &point_2);
+            assert!(
+                (distance - vincenty).abs() / vincenty < 0.005,
+                "{:?} {} {}",
+                metric,
+                distance,
+                vincenty
+            );
+        }
+    }
+
+    #[test]
+    fn utm32n_oslo() {
+        // Oslo Sentralstasjon, roughly 598 km east and 6643 km north in zone 32
+        let [easting, northing] = utm32n(&LatLon::new(59.910_9, 10.752_7));
+
+        assert!((easting - 598_000.0).abs() < 1000.0, "{}", easting);
+        assert!((northing - 6_643_000.0).abs() < 1000.0, "{}", northing);
+    }
+
+    #[test]
+    fn measures_with_the_metric() {
+        let nodes = vec![LatLon::new(59.9109, 10.7527), LatLon::new(59.9270, 10.7004)];
+        let measured = DistanceMetric::Equirectangular.measure(nodes.clone());
+
+        assert_eq!(
+            measured[0].weight(&measured[1]),
+            equirectangular(&nodes[0], &nodes[1])
+        );
+        assert_eq!(measured[1].weight(&measured[1]), 0.0);
+    }
+}
@@ -1,86 +1,158 @@
-// TODO
-// consider creating 1-tree which gives a better lower bound to compare heuristics.
-
-use std::collections::VecDeque;
 use std::fmt::Debug;
 
-use crate::edge::Edge;
+use crate::edge::{Cost, Edge};
+use crate::matrix::DistanceMatrix;
 
 #[derive(Debug, Clone)]
 struct TreeEdge<N: Edge> {
     a: N,
     b: N,
+    // Indices of `a` and `b`
+    ends: (usize, usize),
 }
 
 impl<N: Edge> TreeEdge<N> {
-    fn cost(&self) -> f32 {
-        self.a.weight(&self.b)
+    fn cost(&self) -> Cost {
+        let weight = self.a.weight(&self.b);
+
+        if N::SYMMETRIC {
+            weight
+        } else {
+            weight.min(self.b.weight(&self.a))
+        }
     }
 }
 
 #[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub struct MST<E: Edge + Clone> {
     nodes: Vec<E>,
     edges: Vec<TreeEdge<E>>,
+    matrix: DistanceMatrix,
 }
 
 impl<E: Edge + Clone + Debug> MST<E> {
     pub fn new(nodes: Vec<E>) -> Self {
         Self {
+            matrix: DistanceMatrix::lazy(nodes.len()),
             nodes,
             edges: vec![],
         }
     }
 
-    // Finds MST by using Prim's algo
+    // Uses an already calculated matrix, e.g. the one used by `TSP` for the same nodes.
+    pub fn with_matrix(mut self, matrix: DistanceMatrix) -> Self {
+        assert_eq!(matrix.size(), self.nodes.len());
+        self.matrix = matrix;
+        self
+    }
+
+    // With asymmetric weights the tree uses the cheaper direction of every edge,
+    // so that it stays a lower bound for tours in either direction.
+    fn weight(&self, node_1: usize, node_2: usize) -> Cost {
+        let weight = self.matrix.get(&self.nodes, node_1, node_2);
+
+        if E::SYMMETRIC {
+            weight
+        } else {
+            weight.min(self.matrix.get(&self.nodes, node_2, node_1))
+        }
+    }
+
+    // Finds MST by using Prim's algo. Every pair is looked at, since a tree restricted to
+    // candidate edges can cost more than the MST and would no longer be a lower bound.
     // Assumes first node is the start node. could change this to an arbitrary node
     pub fn solve(&mut self) {
-        let mut nodes = VecDeque::from(self.nodes.clone());
-        let input_nodes_len = nodes.len();
+        if self.nodes.is_empty() {
+            return;
+        }
 
-        let mut visited: Vec<E> = vec![];
+        let n = self.nodes.len();
+
+        let mut visited = vec![false; n];
+        // Cheapest known edge from the tree to every node, as (weight, node in tree)
+        let mut closest: Vec<(Cost, usize)> = vec![(Cost::INFINITY, 0); n];
         let mut edges: Vec<TreeEdge<E>> = vec![];
 
-        if let Some(node) = nodes.pop_front() {
-            visited.push(node);
-        }
+        let mut added = 0;
+        for _ in 0..n {
+            visited[added] = true;
+            if added != 0 {
+                edges.push(self.tree_edge(closest[added].1, added));
+            }
 
-        while visited.len() != input_nodes_len {
-            let mut best_dist = f32::INFINITY;
-            let mut closest_node_index: usize = 0;
-            let mut closest_edge: Option<TreeEdge<E>> = None;
-
-            for i in 0..visited.len() {
-                let v = visited[i].clone();
-                for j in 0..nodes.len() {
-                    let u = &nodes[j];
-                    if v.weight(u) < best_dist {
-                        best_dist = v.weight(u);
-                        closest_node_index = j;
-
-                        closest_edge = Some(TreeEdge {
-                            a: v.clone(),
-                            b: u.clone(),
-                        });
-                    }
+            for u in 0..n {
+                let weight = self.weight(added, u);
+                if !visited[u] && weight < closest[u].0 {
+                    closest[u] = (weight, added);
                 }
             }
 
-            if let Some(found) = closest_edge.clone() {
-                edges.push(found.clone());
-
-                // NOTE: b is the new node in the edge
-                visited.push(found.b.clone());
-                nodes.remove(closest_node_index);
+            let mut best_dist = Cost::INFINITY;
+            for u in 0..n {
+                if !visited[u] && closest[u].0 < best_dist {
+                    best_dist = closest[u].0;
+                    added = u;
+                }
             }
         }
 
         self.edges = edges;
     }
 
-    pub fn calculate_cost(&self) -> f32 {
+    fn tree_edge(&self, a: usize, b: usize) -> TreeEdge<E> {
+        TreeEdge {
+            a: self.nodes[a].clone(),
+            b: self.nodes[b].clone(),
+            ends: (a, b),
+        }
+    }
+
+    // Edges of the tree by the indices of the nodes they connect
+    pub fn edges(&self) -> Vec<(usize, usize)> {
+        self.edges.iter().map(|edge| edge.ends).collect()
+    }
+
+    pub fn calculate_cost(&self) -> Cost {
         self.edges.iter().fold(0.0, |acc, curr| acc + curr.cost())
     }
+
+    // A tour is a 1-tree: a spanning tree of every node but one, plus two edges to that node.
+    // Taking a leaf of the MST as that node, the rest of the tree is still minimal and the leaf's
+    // own edge is its cheapest, so the cheapest 1-tree for a leaf is the MST plus the second
+    // cheapest edge of the leaf. The best leaf gives a tighter lower bound than the MST alone.
+    pub fn calculate_one_tree_cost(&self) -> Cost {
+        let n = self.nodes.len();
+        if n < 3 {
+            return self.calculate_cost();
+        }
+
+        let mut degree = vec![0; n];
+        for edge in self.edges.iter() {
+            degree[edge.ends.0] += 1;
+            degree[edge.ends.1] += 1;
+        }
+
+        let second_cheapest = |leaf: usize| {
+            let mut cheapest = [Cost::INFINITY; 2];
+            for u in (0..n).filter(|u| *u != leaf) {
+                let weight = self.weight(leaf, u);
+                if weight < cheapest[0] {
+                    cheapest = [weight, cheapest[0]];
+                } else if weight < cheapest[1] {
+                    cheapest[1] = weight;
+                }
+            }
+            cheapest[1]
+        };
+
+        let extra = (0..n)
+            .filter(|v| degree[*v] == 1)
+            .map(second_cheapest)
+            .fold(0.0, Cost::max);
+
+        self.calculate_cost() + extra
+    }
 }
 
 #[cfg(test)]
@@ -99,8 +171,8 @@ mod tests {
     }
 
     impl Edge for Node {
-        fn weight(&self, node: &Self) -> f32 {
-            (self.weight + node.weight) as f32
+        fn weight(&self, node: &Self) -> Cost {
+            (self.weight + node.weight) as f64
         }
     }
 
@@ -121,5 +193,8 @@ mod tests {
         mst.solve();
         let cost = mst.calculate_cost();
         assert_eq!(cost, 25.0);
+
+        // Every leaf hangs off node 2, and leaf 5 has its second cheapest edge to node 4
+        assert_eq!(mst.calculate_one_tree_cost(), 33.0);
     }
 }
@@ -0,0 +1,515 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+};
+
+use eyre::eyre;
+use flate2::read::ZlibDecoder;
+
+use crate::latlon::LatLon;
+
+// Features an extract can require that are understood here, anything else is refused instead of
+// being read wrong
+const FEATURES: [&str; 2] = ["OsmSchema-V0.6", "DenseNodes"];
+
+// NOTE: What is read from an extract: nodes with their coordinates and ways with their nodes and
+// tags. Relations and the tags of nodes are skipped.
+#[derive(Debug, Clone)]
+pub enum Element {
+    Node {
+        id: i64,
+        latlon: LatLon,
+    },
+    Way {
+        refs: Vec<i64>,
+        tags: HashMap<String, String>,
+    },
+}
+
+// Reads an OpenStreetMap PBF extract, handing every node and way to `found` in the order they are
+// stored. The format is a sequence of zlib compressed protocol buffer blobs, see
+// https://wiki.openstreetmap.org/wiki/PBF_Format
+pub fn read<R: Read>(mut reader: R, mut found: impl FnMut(Element)) -> eyre::Result<()> {
+    loop {
+        let mut length = [0; 4];
+        match reader.read_exact(&mut length) {
+            Ok(()) => (),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err.into()),
+        }
+
+        let mut header = vec![0; u32::from_be_bytes(length) as usize];
+        reader.read_exact(&mut header)?;
+
+        let mut kind = String::new();
+        let mut size = 0;
+        let mut fields = Fields::new(&header);
+        while let Some((number, field)) = fields.next()? {
+            match number {
+                1 => kind = String::from_utf8_lossy(field.bytes()?).into_owned(),
+                3 => size = field.varint()? as usize,
+                _ => (),
+            }
+        }
+
+        let mut data = vec![0; size];
+        reader.read_exact(&mut data)?;
+
+        match kind.as_str() {
+            "OSMHeader" => features(&blob(&data)?)?,
+            "OSMData" => block(&blob(&data)?, &mut found)?,
+            _ => (),
+        }
+    }
+}
+
+// Contents of a blob, which is either stored as is or compressed
+fn blob(data: &[u8]) -> eyre::Result<Vec<u8>> {
+    let mut fields = Fields::new(data);
+    while let Some((number, field)) = fields.next()? {
+        match number {
+            1 => return Ok(field.bytes()?.to_vec()),
+            3 => {
+                let mut raw = vec![];
+                ZlibDecoder::new(field.bytes()?).read_to_end(&mut raw)?;
+                return Ok(raw);
+            }
+            4..=7 => {
+                return Err(eyre!(
+                    "Only zlib compressed PBF extracts are supported, recompress with osmium"
+                ))
+            }
+            _ => (),
+        }
+    }
+
+    Err(eyre!("PBF blob without any data"))
+}
+
+fn features(header: &[u8]) -> eyre::Result<()> {
+    let mut fields = Fields::new(header);
+    while let Some((number, field)) = fields.next()? {
+        if number == 4 {
+            let feature = String::from_utf8_lossy(field.bytes()?);
+            if !FEATURES.contains(&feature.as_ref()) {
+                return Err(eyre!(
+                    "PBF extract requires unsupported feature {}",
+                    feature
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// NOTE: Coordinates are stored as integers, in units of `granularity` nanodegrees from the offsets.
+struct Grid {
+    granularity: i64,
+    lat_offset: i64,
+    lon_offset: i64,
+}
+
+impl Grid {
+    fn latlon(&self, lat: i64, lon: i64) -> LatLon {
+        LatLon::new(
+            (self.lat_offset + self.granularity * lat) as f64 * 1e-9,
+            (self.lon_offset + self.granularity * lon) as f64 * 1e-9,
+        )
+    }
+}
+
+// A block of elements sharing one table of strings and one grid for their coordinates
+fn block(data: &[u8], found: &mut impl FnMut(Element)) -> eyre::Result<()> {
+    let mut strings: Vec<String> = vec![];
+    let mut groups = vec![];
+    let mut grid = Grid {
+        granularity: 100,
+        lat_offset: 0,
+        lon_offset: 0,
+    };
+
+    let mut fields = Fields::new(data);
+    while let Some((number, field)) = fields.next()? {
+        match number {
+            1 => {
+                let mut table = Fields::new(field.bytes()?);
+                while let Some((number, field)) = table.next()? {
+                    if number == 1 {
+                        strings.push(String::from_utf8_lossy(field.bytes()?).into_owned());
+                    }
+                }
+            }
+            2 => groups.push(field.bytes()?),
+            17 => grid.granularity = field.varint()? as i64,
+            19 => grid.lat_offset = field.varint()? as i64,
+            20 => grid.lon_offset = field.varint()? as i64,
+            _ => (),
+        }
+    }
+
+    for group in groups {
+        let mut fields = Fields::new(group);
+        while let Some((number, field)) = fields.next()? {
+            match number {
+                1 => node(field.bytes()?, &grid, found)?,
+                2 => dense(field.bytes()?, &grid, found)?,
+                3 => way(field.bytes()?, &strings, found)?,
+                _ => (),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn node(data: &[u8], grid: &Grid, found: &mut impl FnMut(Element)) -> eyre::Result<()> {
+    let (mut id, mut lat, mut lon) = (0, 0, 0);
+
+    let mut fields = Fields::new(data);
+    while let Some((number, field)) = fields.next()? {
+        match number {
+            1 => id = zigzag(field.varint()?),
+            8 => lat = zigzag(field.varint()?),
+            9 => lon = zigzag(field.varint()?),
+            _ => (),
+        }
+    }
+
+    found(Element::Node {
+        id,
+        latlon: grid.latlon(lat, lon),
+    });
+
+    Ok(())
+}
+
+// Nodes stored column by column, every value as the difference to the one before it
+fn dense(data: &[u8], grid: &Grid, found: &mut impl FnMut(Element)) -> eyre::Result<()> {
+    let (mut ids, mut lats, mut lons) = (vec![], vec![], vec![]);
+
+    let mut fields = Fields::new(data);
+    while let Some((number, field)) = fields.next()? {
+        match number {
+            1 => ids = deltas(field.varints()?),
+            8 => lats = deltas(field.varints()?),
+            9 => lons = deltas(field.varints()?),
+            _ => (),
+        }
+    }
+
+    if ids.len() != lats.len() || ids.len() != lons.len() {
+        return Err(eyre!("Dense nodes in PBF extract have missing coordinates"));
+    }
+
+    for ((id, lat), lon) in ids.into_iter().zip(lats).zip(lons) {
+        found(Element::Node {
+            id,
+            latlon: grid.latlon(lat, lon),
+        });
+    }
+
+    Ok(())
+}
+
+fn way(data: &[u8], strings: &[String], found: &mut impl FnMut(Element)) -> eyre::Result<()> {
+    let (mut keys, mut values, mut refs) = (vec![], vec![], vec![]);
+
+    let mut fields = Fields::new(data);
+    while let Some((number, field)) = fields.next()? {
+        match number {
+            2 => keys = field.varints()?,
+            3 => values = field.varints()?,
+            8 => refs = deltas(field.varints()?),
+            _ => (),
+        }
+    }
+
+    let string = |i: u64| {
+        strings
+            .get(i as usize)
+            .cloned()
+            .ok_or_else(|| eyre!("Way in PBF extract refers to unknown string {}", i))
+    };
+    let tags = keys
+        .into_iter()
+        .zip(values)
+        .map(|(key, value)| Ok((string(key)?, string(value)?)))
+        .collect::<eyre::Result<_>>()?;
+
+    found(Element::Way { refs, tags });
+
+    Ok(())
+}
+
+// Signed integers are stored so that small negative numbers stay small
+fn zigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+fn deltas(values: Vec<u64>) -> Vec<i64> {
+    values
+        .into_iter()
+        .scan(0, |last, value| {
+            *last += zigzag(value);
+            Some(*last)
+        })
+        .collect()
+}
+
+// NOTE: The value of one field of a protocol buffer message, by how it is encoded.
+enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+impl<'a> Field<'a> {
+    fn varint(self) -> eyre::Result<u64> {
+        match self {
+            Field::Varint(value) => Ok(value),
+            _ => Err(eyre!("Expected a number in PBF extract")),
+        }
+    }
+
+    fn bytes(self) -> eyre::Result<&'a [u8]> {
+        match self {
+            Field::Bytes(bytes) => Ok(bytes),
+            _ => Err(eyre!("Expected bytes in PBF extract")),
+        }
+    }
+
+    // Repeated numbers, which are usually packed one after another into bytes
+    fn varints(self) -> eyre::Result<Vec<u64>> {
+        match self {
+            Field::Varint(value) => Ok(vec![value]),
+            Field::Bytes(mut bytes) => {
+                let mut values = vec![];
+                while !bytes.is_empty() {
+                    values.push(varint(&mut bytes)?);
+                }
+                Ok(values)
+            }
+            Field::Fixed => Err(eyre!("Expected numbers in PBF extract")),
+        }
+    }
+}
+
+// Fields of a protocol buffer message with their numbers, in the order they are stored
+struct Fields<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn next(&mut self) -> eyre::Result<Option<(u64, Field<'a>)>> {
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+
+        let key = varint(&mut self.data)?;
+        let field = match key & 7 {
+            0 => Field::Varint(varint(&mut self.data)?),
+            1 => {
+                self.skip(8)?;
+                Field::Fixed
+            }
+            2 => {
+                let length = varint(&mut self.data)? as usize;
+                Field::Bytes(self.skip(length)?)
+            }
+            5 => {
+                self.skip(4)?;
+                Field::Fixed
+            }
+            _ => return Err(eyre!("Unknown wire type in PBF extract")),
+        };
+
+        Ok(Some((key >> 3, field)))
+    }
+
+    fn skip(&mut self, length: usize) -> eyre::Result<&'a [u8]> {
+        if length > self.data.len() {
+            return Err(eyre!("PBF extract ends in the middle of a message"));
+        }
+
+        let (skipped, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(skipped)
+    }
+}
+
+fn varint(data: &mut &[u8]) -> eyre::Result<u64> {
+    let mut value = 0;
+
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = data
+            .split_first()
+            .ok_or_else(|| eyre!("PBF extract ends in the middle of a number"))?;
+        *data = rest;
+
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(eyre!("Number in PBF extract is too long"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::ZlibEncoder, Compression};
+
+    use super::*;
+
+    fn varint(out: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn number(out: &mut Vec<u8>, number: u64, value: u64) {
+        varint(out, number << 3);
+        varint(out, value);
+    }
+
+    fn bytes(out: &mut Vec<u8>, number: u64, bytes: &[u8]) {
+        varint(out, number << 3 | 2);
+        varint(out, bytes.len() as u64);
+        out.extend_from_slice(bytes);
+    }
+
+    fn packed(out: &mut Vec<u8>, number: u64, values: &[u64]) {
+        let mut packed = vec![];
+        for value in values {
+            varint(&mut packed, *value);
+        }
+        bytes(out, number, &packed);
+    }
+
+    fn signed(value: i64) -> u64 {
+        ((value << 1) ^ (value >> 63)) as u64
+    }
+
+    fn delta_coded(values: &[i64]) -> Vec<u64> {
+        let mut last = 0;
+        values
+            .iter()
+            .map(|value| {
+                let delta = value - last;
+                last = *value;
+                signed(delta)
+            })
+            .collect()
+    }
+
+    fn file_block(out: &mut Vec<u8>, kind: &str, contents: &[u8]) -> eyre::Result<()> {
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(contents)?;
+
+        let mut blob = vec![];
+        number(&mut blob, 2, contents.len() as u64);
+        bytes(&mut blob, 3, &encoder.finish()?);
+
+        let mut header = vec![];
+        bytes(&mut header, 1, kind.as_bytes());
+        number(&mut header, 3, blob.len() as u64);
+
+        out.extend_from_slice(&(header.len() as u32).to_be_bytes());
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&blob);
+
+        Ok(())
+    }
+
+    // Two dense nodes and one plain node, west of Oslo S, joined by a footway
+    fn extract(feature: &str) -> eyre::Result<Vec<u8>> {
+        let mut header = vec![];
+        bytes(&mut header, 4, b"OsmSchema-V0.6");
+        bytes(&mut header, 4, feature.as_bytes());
+
+        let mut strings = vec![];
+        for string in ["", "highway", "footway"] {
+            bytes(&mut strings, 1, string.as_bytes());
+        }
+
+        let mut dense = vec![];
+        packed(&mut dense, 1, &delta_coded(&[1, 2]));
+        packed(&mut dense, 8, &delta_coded(&[599100000, 599145000]));
+        packed(&mut dense, 9, &delta_coded(&[107600000, 107600000]));
+
+        let mut node = vec![];
+        number(&mut node, 1, signed(3));
+        number(&mut node, 8, signed(599145000));
+        number(&mut node, 9, signed(107610000));
+
+        let mut way = vec![];
+        number(&mut way, 1, 10);
+        packed(&mut way, 2, &[1]);
+        packed(&mut way, 3, &[2]);
+        packed(&mut way, 8, &delta_coded(&[1, 2, 3]));
+
+        let mut group = vec![];
+        bytes(&mut group, 2, &dense);
+        bytes(&mut group, 1, &node);
+        bytes(&mut group, 3, &way);
+
+        let mut block = vec![];
+        bytes(&mut block, 1, &strings);
+        bytes(&mut block, 2, &group);
+
+        let mut pbf = vec![];
+        file_block(&mut pbf, "OSMHeader", &header)?;
+        file_block(&mut pbf, "OSMData", &block)?;
+
+        Ok(pbf)
+    }
+
+    #[test]
+    fn reads_nodes_and_ways() -> eyre::Result<()> {
+        let mut elements = vec![];
+        read(extract("DenseNodes")?.as_slice(), |element| {
+            elements.push(element)
+        })?;
+
+        let nodes: Vec<(i64, f64, f64)> = elements
+            .iter()
+            .filter_map(|element| match element {
+                Element::Node { id, latlon } => Some((*id, latlon.lat(), latlon.lon())),
+                _ => None,
+            })
+            .collect();
+        let expected = [(1, 59.91, 10.76), (2, 59.9145, 10.76), (3, 59.9145, 10.761)];
+        assert_eq!(nodes.len(), expected.len());
+        for ((id, lat, lon), (expected_id, expected_lat, expected_lon)) in
+            nodes.iter().zip(expected)
+        {
+            assert_eq!(*id, expected_id);
+            assert!((lat - expected_lat).abs() < 1e-9 && (lon - expected_lon).abs() < 1e-9);
+        }
+
+        let Some(Element::Way { refs, tags }) = elements.last() else {
+            panic!("Expected the way last, got {:?}", elements.last());
+        };
+        assert_eq!(refs, &[1, 2, 3]);
+        assert_eq!(tags.get("highway").map(String::as_str), Some("footway"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_unknown_features() -> eyre::Result<()> {
+        let found = read(extract("HistoricalInformation")?.as_slice(), |_| ());
+        assert!(found.is_err());
+
+        Ok(())
+    }
+}
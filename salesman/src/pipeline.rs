@@ -0,0 +1,248 @@
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use eyre::eyre;
+
+use crate::{
+    edge::{Cost, Edge},
+    tour::Tour,
+    tsp_ext::{
+        nn::NearestNeighbour,
+        or_opt::OrOpt,
+        solver::{Constructor, Improver, Problem},
+        three_opt::{SimulatedAnnealing, ThreeOpt},
+        two_opt::TwoOpt,
+    },
+};
+
+// NOTE: How the tour looked after one stage of a pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stage {
+    pub name: &'static str,
+    pub cost: Cost,
+    pub elapsed: Duration,
+}
+
+// A constructor followed by improvers that each start from the tour the one before left behind.
+pub struct Pipeline<E> {
+    constructor: Box<dyn Constructor<E>>,
+    improvers: Vec<Box<dyn Improver<E>>>,
+}
+
+impl<E: Edge> Pipeline<E> {
+    pub fn new(constructor: Box<dyn Constructor<E>>) -> Self {
+        Self {
+            constructor,
+            improvers: vec![],
+        }
+    }
+
+    pub fn then(mut self, improver: Box<dyn Improver<E>>) -> Self {
+        self.improvers.push(improver);
+        self
+    }
+
+    // The final tour, and the cost after every stage along with the time it took
+    pub fn run(&self, problem: &Problem<'_, E>) -> (Tour, Vec<Stage>) {
+        let started = Instant::now();
+        let mut tour = self.constructor.construct(problem);
+        let mut stages = vec![Stage {
+            name: self.constructor.name(),
+            cost: problem.cost(&tour),
+            elapsed: started.elapsed(),
+        }];
+
+        for improver in self.improvers.iter() {
+            let started = Instant::now();
+            tour = improver.improve(problem, tour);
+            stages.push(Stage {
+                name: improver.name(),
+                cost: problem.cost(&tour),
+                elapsed: started.elapsed(),
+            });
+        }
+
+        (tour, stages)
+    }
+}
+
+// NOTE: Constructors that can be named in a pipeline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Construction {
+    Nn,
+}
+
+impl Construction {
+    const ALL: [Self; 1] = [Self::Nn];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Nn => "nn",
+        }
+    }
+
+    fn constructor<E: Edge>(&self) -> Box<dyn Constructor<E>> {
+        match self {
+            Self::Nn => Box::new(NearestNeighbour),
+        }
+    }
+}
+
+// NOTE: Improvers that can be named in a pipeline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Improvement {
+    OrOpt,
+    TwoOpt,
+    ThreeOpt,
+    ThreeOptSm,
+}
+
+impl Improvement {
+    const ALL: [Self; 4] = [Self::OrOpt, Self::TwoOpt, Self::ThreeOpt, Self::ThreeOptSm];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::OrOpt => "or_opt",
+            Self::TwoOpt => "two_opt",
+            Self::ThreeOpt => "three_opt",
+            Self::ThreeOptSm => "three_opt_sm",
+        }
+    }
+
+    fn improver<E: Edge>(&self) -> Box<dyn Improver<E>> {
+        match self {
+            Self::OrOpt => Box::new(OrOpt),
+            Self::TwoOpt => Box::new(TwoOpt),
+            Self::ThreeOpt => Box::new(ThreeOpt),
+            Self::ThreeOptSm => Box::new(SimulatedAnnealing),
+        }
+    }
+}
+
+// NOTE: The solvers of a pipeline by name, e.g. `nn -> or_opt -> three_opt`, to be built into a
+// `Pipeline` once the kind of node is known. The first one builds the tour, so it has to be nn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipelineConfig {
+    construction: Construction,
+    improvements: Vec<Improvement>,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            construction: Construction::Nn,
+            improvements: vec![Improvement::ThreeOptSm],
+        }
+    }
+}
+
+impl PipelineConfig {
+    pub fn build<E: Edge>(&self) -> Pipeline<E> {
+        self.improvements.iter().fold(
+            Pipeline::new(self.construction.constructor()),
+            |pipeline, improvement| pipeline.then(improvement.improver()),
+        )
+    }
+}
+
+impl FromStr for PipelineConfig {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut names = s.split("->").map(|name| name.trim());
+
+        let first = names.next().unwrap_or_default();
+        let construction = Construction::ALL
+            .into_iter()
+            .find(|construction| construction.name() == first)
+            .ok_or_else(|| eyre!("A pipeline starts with nn to build the tour, got {}", s))?;
+
+        let improvements = names
+            .map(|name| {
+                Improvement::ALL
+                    .into_iter()
+                    .find(|improvement| improvement.name() == name)
+                    .ok_or_else(|| {
+                        eyre!(
+                            "Unknown improver {}, expected or_opt, two_opt, three_opt or three_opt_sm",
+                            name
+                        )
+                    })
+            })
+            .collect::<eyre::Result<Vec<Improvement>>>()?;
+
+        Ok(Self {
+            construction,
+            improvements,
+        })
+    }
+}
+
+impl fmt::Display for PipelineConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = std::iter::once(self.construction.name())
+            .chain(
+                self.improvements
+                    .iter()
+                    .map(|improvement| improvement.name()),
+            )
+            .collect();
+        write!(f, "{}", names.join(" -> "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::DistanceMatrix;
+
+    #[derive(Clone, Debug)]
+    struct OneDPoint {
+        x: f64,
+    }
+
+    impl Edge for OneDPoint {
+        fn weight(&self, node: &Self) -> Cost {
+            (self.x - node.x).abs()
+        }
+    }
+
+    #[test]
+    fn parses_pipelines() -> eyre::Result<()> {
+        let pipeline: PipelineConfig = "nn->or_opt ->  three_opt".parse()?;
+        assert_eq!(pipeline.to_string(), "nn -> or_opt -> three_opt");
+
+        assert!("or_opt -> three_opt".parse::<PipelineConfig>().is_err());
+        assert!("nn -> nn".parse::<PipelineConfig>().is_err());
+        assert!("nn -> four_opt".parse::<PipelineConfig>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn records_every_stage() {
+        let nodes: Vec<OneDPoint> = [0.0, 5.0, 1.0, 4.0, 2.0, 3.0, 9.0, 6.0, 8.0, 7.0]
+            .into_iter()
+            .map(|x| OneDPoint { x })
+            .collect();
+        let matrix = DistanceMatrix::dense(&nodes);
+        let problem = Problem::new(&nodes, &matrix);
+
+        let pipeline = Pipeline::new(Box::new(NearestNeighbour))
+            .then(Box::new(TwoOpt))
+            .then(Box::new(OrOpt));
+        let (tour, stages) = pipeline.run(&problem);
+
+        let names: Vec<&str> = stages.iter().map(|stage| stage.name).collect();
+        assert_eq!(names, ["nn", "two_opt", "or_opt"]);
+        assert!(stages.windows(2).all(|pair| pair[1].cost <= pair[0].cost));
+        assert_eq!(
+            stages.last().map(|stage| stage.cost),
+            Some(problem.cost(&tour))
+        );
+        assert_eq!(problem.cost(&tour), 18.0);
+    }
+}
@@ -0,0 +1,183 @@
+use std::{fmt::Debug, path::Path, str::FromStr};
+
+use eyre::eyre;
+use serde::Deserialize;
+
+use crate::{
+    edge::Edge,
+    latlon::{LatLon, StartingPoint},
+    precomputed::Precomputed,
+};
+
+// Used when no file with starting points is given
+const STARTING_POINTS: &str = include_str!("../starting_points.csv");
+
+// NOTE: One row in a starting points CSV. `station` marks T-bane stations, which are the ones
+// considered when looking for the station closest to a location.
+#[derive(Debug, Deserialize)]
+struct Row {
+    name: String,
+    lat: f64,
+    lon: f64,
+    #[serde(default)]
+    station: bool,
+}
+
+// Starting points that can be picked by name, loaded from a CSV with name, lat, lon and an
+// optional station column.
+#[derive(Debug, Clone)]
+pub struct StartingPoints {
+    points: Vec<StartingPoint>,
+    stations: Vec<bool>,
+}
+
+impl Default for StartingPoints {
+    fn default() -> Self {
+        Self::parse(STARTING_POINTS.as_bytes()).expect("Built-in starting points are valid")
+    }
+}
+
+impl StartingPoints {
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        Self::from_reader(csv::Reader::from_path(path)?)
+    }
+
+    fn parse(csv: &[u8]) -> eyre::Result<Self> {
+        Self::from_reader(csv::Reader::from_reader(csv))
+    }
+
+    fn from_reader<R: std::io::Read>(mut rdr: csv::Reader<R>) -> eyre::Result<Self> {
+        let mut points = vec![];
+        let mut stations = vec![];
+
+        for record in rdr.deserialize() {
+            let row: Row = record?;
+            points.push(StartingPoint::new(LatLon::new(row.lat, row.lon), row.name));
+            stations.push(row.station);
+        }
+
+        Ok(Self { points, stations })
+    }
+
+    pub fn find(&self, place: &Place) -> eyre::Result<StartingPoint> {
+        match place {
+            Place::Named(name) => self
+                .points
+                .iter()
+                .find(|point| point.name().to_lowercase() == name.to_lowercase())
+                .cloned()
+                .ok_or_else(|| {
+                    let names: Vec<&str> = self.points.iter().map(|point| point.name()).collect();
+                    eyre!(
+                        "Unknown starting point {}, expected one of {}",
+                        name,
+                        names.join(", ")
+                    )
+                }),
+            Place::At(latlon) => Ok(StartingPoint::new(
+                latlon.clone(),
+                format!("{}, {}", latlon.lat(), latlon.lon()),
+            )),
+            Place::NearestStation(latlon) => self
+                .points
+                .iter()
+                .zip(self.stations.iter())
+                .filter(|(_, station)| **station)
+                .map(|(point, _)| point)
+                .min_by(|a, b| {
+                    let a = a.latlon().calculate_distance_to(latlon);
+                    let b = b.latlon().calculate_distance_to(latlon);
+                    a.total_cmp(&b)
+                })
+                .cloned()
+                .ok_or_else(|| eyre!("None of the starting points are T-bane stations")),
+        }
+    }
+}
+
+// How a starting or end point is picked on the command line: a name from the starting points,
+// raw coordinates as `lat,lon`, or `nearest:lat,lon` for the T-bane station closest to them.
+#[derive(Debug, Clone)]
+pub enum Place {
+    Named(String),
+    At(LatLon),
+    NearestStation(LatLon),
+}
+
+impl FromStr for Place {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(latlon) = s.strip_prefix("nearest:") {
+            return Ok(Self::NearestStation(parse_latlon(latlon).ok_or_else(
+                || eyre!("Expected nearest:<lat>,<lon>, got {}", s),
+            )?));
+        }
+
+        match parse_latlon(s) {
+            Some(latlon) => Ok(Self::At(latlon)),
+            None => Ok(Self::Named(s.to_string())),
+        }
+    }
+}
+
+fn parse_latlon(s: &str) -> Option<LatLon> {
+    let (lat, lon) = s.split_once(',')?;
+    let lat: f64 = lat.trim().parse().ok()?;
+    let lon: f64 = lon.trim().parse().ok()?;
+
+    ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon))
+        .then(|| LatLon::new(lat, lon))
+}
+
+// Turns a tour into a path from the first node to the last one. The last node is folded into
+// the first, so that every edge into the first node is weighed as an edge into the last one,
+// and the solvers can keep working on closed tours.
+pub fn open<N: Edge>(mut nodes: Vec<N>) -> Vec<Precomputed<N>> {
+    let Some(end) = nodes.pop() else {
+        return vec![];
+    };
+
+    Precomputed::calculate(nodes, |nodes, i, j| match j {
+        0 => nodes[i].weight(&end),
+        _ => nodes[i].weight(&nodes[j]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_places() -> eyre::Result<()> {
+        let points = StartingPoints::default();
+
+        let kampen = points.find(&"kampen".parse()?)?;
+        assert_eq!(kampen.name(), "Kampen");
+
+        // Right next to Kampen, which is not a station
+        let nearest = points.find(&"nearest:59.9134,10.7746".parse()?)?;
+        assert_eq!(nearest.name(), "Tøyen T-bane");
+
+        let at = points.find(&"59.9, 10.7".parse()?)?;
+        assert_eq!(at.latlon().lat(), 59.9);
+
+        assert!(points.find(&"Narnia".parse()?).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn open_path_ends_elsewhere() {
+        let nodes = vec![
+            LatLon::new(59.91, 10.70),
+            LatLon::new(59.91, 10.71),
+            LatLon::new(59.91, 10.72),
+        ];
+        let ended = open(nodes.clone());
+
+        assert_eq!(ended.len(), 2);
+        assert_eq!(ended[0].weight(&ended[1]), nodes[0].weight(&nodes[1]));
+        assert_eq!(ended[1].weight(&ended[0]), nodes[1].weight(&nodes[2]));
+    }
+}
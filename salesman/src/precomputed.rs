@@ -0,0 +1,79 @@
+use std::{
+    fmt::{self, Debug},
+    rc::Rc,
+};
+
+use crate::{
+    edge::{Cost, Edge, Geographic, Spatial},
+    latlon::LatLon,
+};
+
+// A node whose weight to other nodes is looked up in a matrix calculated up front for every pair
+// of nodes it was created with, e.g. distances through a street network or travel times. The
+// matrix is shared by all of them, and `SYMMETRIC` tells the solvers whether it is.
+#[derive(Clone)]
+pub struct Precomputed<N, const SYMMETRIC: bool = false> {
+    node: N,
+    index: usize,
+    size: usize,
+    weights: Rc<Vec<Cost>>,
+}
+
+impl<N, const SYMMETRIC: bool> Precomputed<N, SYMMETRIC> {
+    // Wraps the nodes with the weights between every pair of them, row by row
+    pub fn wrap(nodes: Vec<N>, weights: Vec<Cost>) -> Vec<Self> {
+        let size = nodes.len();
+        assert_eq!(weights.len(), size * size);
+        let weights = Rc::new(weights);
+
+        nodes
+            .into_iter()
+            .enumerate()
+            .map(|(index, node)| Self {
+                node,
+                index,
+                size,
+                weights: weights.clone(),
+            })
+            .collect()
+    }
+
+    // Wraps the nodes with `weight` from every node to every other node, given all the nodes and
+    // the indices of the two
+    pub fn calculate(nodes: Vec<N>, weight: impl Fn(&[N], usize, usize) -> Cost) -> Vec<Self> {
+        let n = nodes.len();
+        let weights = (0..n)
+            .flat_map(|i| (0..n).map(move |j| (i, j)))
+            .map(|(i, j)| if i == j { 0.0 } else { weight(&nodes, i, j) })
+            .collect();
+
+        Self::wrap(nodes, weights)
+    }
+}
+
+// Leaves the weights out, they are the same for every node and would drown the output
+impl<N: Debug, const SYMMETRIC: bool> Debug for Precomputed<N, SYMMETRIC> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.node.fmt(f)
+    }
+}
+
+impl<N, const SYMMETRIC: bool> Edge for Precomputed<N, SYMMETRIC> {
+    const SYMMETRIC: bool = SYMMETRIC;
+
+    fn weight(&self, node: &Self) -> Cost {
+        self.weights[self.index * self.size + node.index]
+    }
+}
+
+impl<N: Geographic, const SYMMETRIC: bool> Geographic for Precomputed<N, SYMMETRIC> {
+    fn latlon(&self) -> LatLon {
+        self.node.latlon()
+    }
+}
+
+impl<N: Spatial, const SYMMETRIC: bool> Spatial for Precomputed<N, SYMMETRIC> {
+    fn position(&self) -> [f64; 2] {
+        self.node.position()
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use eyre::eyre;
+use flate2::{write::ZlibEncoder, Compression, Crc};
+
+use crate::{
+    edge::{NodeLatLon, Spatial},
+    tour,
+};
+
+// Width of the image in pixels, the height follows from the area the stops cover
+const WIDTH: f64 = 800.0;
+// Empty space around the stops, so that the labels of the outermost ones fit
+const MARGIN: f64 = 30.0;
+
+const BACKGROUND: [u8; 3] = [255, 255, 255];
+const ROUTE: [u8; 3] = [31, 95, 191];
+const TREE: [u8; 3] = [170, 170, 170];
+const STOP: [u8; 3] = [40, 40, 40];
+const START: [u8; 3] = [214, 39, 40];
+
+// Digits 0-9 as 3x5 pixel glyphs, one row per byte with the leftmost pixel in the highest bit
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+// Draws the tour to an SVG or PNG file, picked by the extension of `path`. Stops are numbered in
+// visiting order and the start is drawn in red. The edges of `tree` are drawn below the tour.
+pub fn save(
+    path: impl AsRef<Path>,
+    stops: &[NodeLatLon],
+    order: &[usize],
+    tree: Option<&[(usize, usize)]>,
+) -> eyre::Result<()> {
+    let path = path.as_ref();
+    let scene = Scene::new(stops, order, tree.unwrap_or_default());
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("svg") => fs::write(path, scene.svg())?,
+        Some("png") => fs::write(path, scene.png()?)?,
+        _ => {
+            return Err(eyre!(
+                "Can only render to .svg or .png, got {}",
+                path.display()
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+// The tour drawn as SVG, to be embedded elsewhere
+pub fn svg(stops: &[NodeLatLon], order: &[usize], tree: Option<&[(usize, usize)]>) -> String {
+    Scene::new(stops, order, tree.unwrap_or_default()).svg()
+}
+
+// NOTE: The tour projected to pixels, with the origin in the top left corner. Stops are kept by
+// their index in the path, like in `order` and `tree`.
+struct Scene<'a> {
+    width: u32,
+    height: u32,
+    points: Vec<[f64; 2]>,
+    order: &'a [usize],
+    tree: &'a [(usize, usize)],
+}
+
+impl<'a> Scene<'a> {
+    fn new(stops: &[NodeLatLon], order: &'a [usize], tree: &'a [(usize, usize)]) -> Self {
+        let positions: Vec<[f64; 2]> = stops.iter().map(|stop| stop.position()).collect();
+
+        let [min_x, min_y, max_x, max_y] = positions.iter().fold(
+            [
+                f64::INFINITY,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::NEG_INFINITY,
+            ],
+            |[min_x, min_y, max_x, max_y], [x, y]| {
+                [min_x.min(*x), min_y.min(*y), max_x.max(*x), max_y.max(*y)]
+            },
+        );
+        // A single stop, or stops on a line, still get an image of some size
+        let span = (max_x - min_x).max(max_y - min_y).max(1.0);
+        let scale = (WIDTH - 2.0 * MARGIN) / span;
+        let height = ((max_y - min_y) * scale + 2.0 * MARGIN).ceil();
+
+        let points = positions
+            .iter()
+            .map(|[x, y]| {
+                [
+                    MARGIN + (x - min_x) * scale,
+                    // North is up
+                    MARGIN + (max_y - y) * scale,
+                ]
+            })
+            .collect();
+
+        Self {
+            width: WIDTH as u32,
+            height: height as u32,
+            points,
+            order,
+            tree,
+        }
+    }
+
+    // Stops in visiting order with their number, the start only once for a round trip
+    fn visits(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        tour::visits(self.order).iter().copied().enumerate()
+    }
+
+    fn svg(&self) -> String {
+        let mut svg = String::new();
+        let colour = |[r, g, b]: [u8; 3]| format!("#{:02x}{:02x}{:02x}", r, g, b);
+
+        // Writing to a String cannot fail
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+            self.width, self.height
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect width="100%" height="100%" fill="{}"/>"#,
+            colour(BACKGROUND)
+        );
+
+        for (a, b) in self.tree {
+            let ([x1, y1], [x2, y2]) = (self.points[*a], self.points[*b]);
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-dasharray="4 3"/>"#,
+                x1,
+                y1,
+                x2,
+                y2,
+                colour(TREE)
+            );
+        }
+
+        let route: Vec<String> = self
+            .order
+            .iter()
+            .map(|i| format!("{:.1},{:.1}", self.points[*i][0], self.points[*i][1]))
+            .collect();
+        let _ = writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+            route.join(" "),
+            colour(ROUTE)
+        );
+
+        for (p, i) in self.visits() {
+            let [x, y] = self.points[i];
+            let (radius, fill) = if p == 0 { (6, START) } else { (4, STOP) };
+            let _ = writeln!(
+                svg,
+                r#"<circle cx="{:.1}" cy="{:.1}" r="{}" fill="{}"/>"#,
+                x,
+                y,
+                radius,
+                colour(fill)
+            );
+            let _ = writeln!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}" font-family="sans-serif" font-size="11">{}</text>"#,
+                x + 6.0,
+                y - 6.0,
+                p + 1
+            );
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn png(&self) -> io::Result<Vec<u8>> {
+        let mut canvas = Canvas::new(self.width, self.height);
+
+        for (a, b) in self.tree {
+            canvas.line(self.points[*a], self.points[*b], 0, TREE);
+        }
+        for pair in self.order.windows(2) {
+            canvas.line(self.points[pair[0]], self.points[pair[1]], 1, ROUTE);
+        }
+        for (p, i) in self.visits() {
+            let [x, y] = self.points[i];
+            if p == 0 {
+                canvas.disc([x, y], 6.0, START);
+            } else {
+                canvas.disc([x, y], 4.0, STOP);
+            }
+            canvas.number([x + 6.0, y - 16.0], p + 1, STOP);
+        }
+
+        canvas.encode()
+    }
+}
+
+// NOTE: RGB pixels, row by row from the top. Anything drawn outside is clipped.
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: BACKGROUND.repeat((width * height) as usize),
+        }
+    }
+
+    fn set(&mut self, x: i64, y: i64, colour: [u8; 3]) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let i = 3 * (y as usize * self.width as usize + x as usize);
+        self.pixels[i..i + 3].copy_from_slice(&colour);
+    }
+
+    // Square brush of `thickness` pixels on every side of the centre, moved in half pixel steps
+    fn line(&mut self, [x1, y1]: [f64; 2], [x2, y2]: [f64; 2], thickness: i64, colour: [u8; 3]) {
+        let steps = ((x2 - x1).abs().max((y2 - y1).abs()) * 2.0).ceil().max(1.0) as usize;
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let x = (x1 + (x2 - x1) * t).round() as i64;
+            let y = (y1 + (y2 - y1) * t).round() as i64;
+            for dy in -thickness..=thickness {
+                for dx in -thickness..=thickness {
+                    self.set(x + dx, y + dy, colour);
+                }
+            }
+        }
+    }
+
+    fn disc(&mut self, [cx, cy]: [f64; 2], radius: f64, colour: [u8; 3]) {
+        let r = radius.ceil() as i64;
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if ((dx * dx + dy * dy) as f64) <= radius * radius {
+                    self.set(cx.round() as i64 + dx, cy.round() as i64 + dy, colour);
+                }
+            }
+        }
+    }
+
+    // Digits twice their glyph size, starting at the top left corner `[x, y]`
+    fn number(&mut self, [x, y]: [f64; 2], number: usize, colour: [u8; 3]) {
+        const SCALE: i64 = 2;
+
+        for (d, digit) in number.to_string().bytes().enumerate() {
+            let glyph = DIGITS[(digit - b'0') as usize];
+            let left = x.round() as i64 + d as i64 * 4 * SCALE;
+
+            for (row, bits) in glyph.iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) == 0 {
+                        continue;
+                    }
+                    for dy in 0..SCALE {
+                        for dx in 0..SCALE {
+                            self.set(
+                                left + column * SCALE + dx,
+                                y.round() as i64 + row as i64 * SCALE + dy,
+                                colour,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // 8-bit RGB PNG without filtering
+    fn encode(&self) -> io::Result<Vec<u8>> {
+        let stride = 3 * self.width as usize;
+        let mut raw = Vec::with_capacity((stride + 1) * self.height as usize);
+        for row in self.pixels.chunks(stride) {
+            // Filter type of the row
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(&raw)?;
+        let data = encoder.finish()?;
+
+        let mut header = vec![];
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // Bit depth, RGB colour type, and the default compression, filter and interlace methods
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        chunk(&mut png, b"IHDR", &header);
+        chunk(&mut png, b"IDAT", &data);
+        chunk(&mut png, b"IEND", &[]);
+
+        Ok(png)
+    }
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend_from_slice(&crc.sum().to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::ZlibDecoder;
+
+    use super::*;
+    use crate::statue::{test_stops, Statue};
+
+    // A third stop, so that the tour is more than a line there and back
+    fn stops() -> Vec<NodeLatLon> {
+        let mut stops = test_stops();
+        stops.push(NodeLatLon::Statue(Statue::new(
+            "Tigeren", "", "", 59.9106, 10.7512, "",
+        )));
+        stops
+    }
+
+    #[test]
+    fn draws_svg() {
+        let stops = stops();
+        let order = [0, 2, 1, 0];
+        let scene = Scene::new(&stops, &order, &[(0, 2), (2, 1)]);
+
+        assert_eq!(scene.width, 800);
+        for [x, y] in scene.points.iter() {
+            assert!((MARGIN..=WIDTH - MARGIN).contains(x));
+            assert!((MARGIN..=scene.height as f64 - MARGIN).contains(y));
+        }
+        // The northernmost stop is at the top
+        assert_eq!(scene.points[1][1], MARGIN);
+
+        let svg = scene.svg();
+        assert_eq!(svg.matches("<circle").count(), 3);
+        assert_eq!(svg.matches("<line").count(), 2);
+        assert!(svg.contains(">3</text>"));
+    }
+
+    #[test]
+    fn encodes_png() -> eyre::Result<()> {
+        let stops = stops();
+        let scene = Scene::new(&stops, &[0, 2, 1, 0], &[]);
+        let png = scene.png()?;
+
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..20], &scene.width.to_be_bytes());
+        assert_eq!(&png[20..24], &scene.height.to_be_bytes());
+
+        // IHDR takes 25 bytes after the signature, with its length, kind and checksum
+        let length = u32::from_be_bytes(png[33..37].try_into()?) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let mut raw = vec![];
+        ZlibDecoder::new(&png[41..41 + length]).read_to_end(&mut raw)?;
+        assert_eq!(
+            raw.len(),
+            (3 * scene.width as usize + 1) * scene.height as usize
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use serde::Serialize;
+
+use crate::{
+    config::Profile,
+    edge::{Cost, Geographic, NodeLatLon},
+    pipeline::Stage,
+};
+
+// NOTE: The result of a run as printed with `--output json`. Scripts read it, so fields are only
+// ever added, never renamed or removed. Costs are in metres for distance and minutes for time.
+#[derive(Debug, Serialize)]
+pub struct Report<'a> {
+    // Settings the run was made with, after merging the command line with the config file
+    pub parameters: &'a Profile,
+    pub pipeline: String,
+    pub seed: Option<u64>,
+    // "distance" or "time"
+    pub quantity: &'a str,
+    pub cost: Cost,
+    pub mst: Cost,
+    pub lower_bound: Cost,
+    pub stages: Vec<Timing>,
+    // In visiting order, a round trip ends with the first stop again
+    pub stops: Vec<Stop>,
+    // From every stop to the next one
+    pub legs: Vec<Leg>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Timing {
+    pub name: &'static str,
+    pub cost: Cost,
+    pub seconds: f64,
+}
+
+impl From<&Stage> for Timing {
+    fn from(stage: &Stage) -> Self {
+        Self {
+            name: stage.name,
+            cost: stage.cost,
+            seconds: stage.elapsed.as_secs_f64(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Stop {
+    pub name: String,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    pub address: Option<String>,
+    pub link: Option<String>,
+}
+
+impl From<&NodeLatLon> for Stop {
+    fn from(node: &NodeLatLon) -> Self {
+        let latlon = node.latlon();
+        let (address, link) = match node {
+            NodeLatLon::Statue(statue) => (
+                Some(statue.address().to_string()),
+                Some(statue.link().to_string()),
+            ),
+            NodeLatLon::StartingPoint(_) => (None, None),
+        };
+
+        Self {
+            name: node.name().to_string(),
+            lat: Some(latlon.lat()),
+            lon: Some(latlon.lon()),
+            address,
+            link,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Leg {
+    pub cost: Cost,
+    // Metres, left out where there is no map to measure on
+    pub distance: Option<Cost>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::statue::test_stops;
+
+    #[test]
+    fn keeps_its_schema() -> eyre::Result<()> {
+        let parameters = Profile::default();
+        let start = &test_stops()[0];
+        let report = Report {
+            parameters: &parameters,
+            pipeline: "nn".to_string(),
+            seed: Some(1),
+            quantity: "distance",
+            cost: 0.0,
+            mst: 0.0,
+            lower_bound: 0.0,
+            stages: vec![Timing::from(&Stage {
+                name: "nn",
+                cost: 0.0,
+                elapsed: Duration::from_millis(1500),
+            })],
+            stops: vec![Stop::from(start)],
+            legs: vec![],
+        };
+
+        let value = serde_json::to_value(&report)?;
+        let mut keys: Vec<&String> = value.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [
+                "cost",
+                "legs",
+                "lower_bound",
+                "mst",
+                "parameters",
+                "pipeline",
+                "quantity",
+                "seed",
+                "stages",
+                "stops"
+            ]
+        );
+        assert_eq!(
+            value["stages"][0],
+            json!({ "name": "nn", "cost": 0.0, "seconds": 1.5 })
+        );
+        assert_eq!(
+            value["stops"][0],
+            json!({
+                "name": "Grønland T-bane",
+                "lat": 59.9127,
+                "lon": 10.7607,
+                "address": null,
+                "link": null
+            })
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,491 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use eyre::eyre;
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use serde_json::{Map, Value};
+
+use crate::statue::MaybeStatue;
+
+// NOTE: Statues as they were read, before anything is filled in, with the reason for every row
+// that could not be read at all. A missing title is always filled in. Everything but the CSV is
+// normalized from free-form properties or tags, where a missing address is no reason to leave a
+// statue out either, so addresses are also filled in with `fallbacks` set. `header` is set when
+// the rows were preceded by a header line, which is not counted as a row.
+pub struct Input {
+    pub rows: Vec<Result<MaybeStatue, String>>,
+    pub fallbacks: bool,
+    pub header: bool,
+}
+
+impl Input {
+    // What the tour is planned with, which needs every row to be readable
+    pub fn filled(mut self) -> eyre::Result<Vec<MaybeStatue>> {
+        let rows = std::mem::take(&mut self.rows);
+
+        rows.into_iter()
+            .enumerate()
+            .map(|(i, row)| match row {
+                Ok(statue) => Ok(self.fill(statue)),
+                Err(reason) => Err(eyre!("Row {} can't be read: {}", i + 1, reason)),
+            })
+            .collect()
+    }
+
+    // The statue with everything that can be filled in filled in
+    pub fn fill(&self, statue: MaybeStatue) -> MaybeStatue {
+        match self.fallbacks {
+            true => statue.with_fallbacks(),
+            false => statue.with_title(),
+        }
+    }
+}
+
+// Values of `artwork_type` that count as a statue when reading OSM extracts
+const ARTWORK_TYPES: [&str; 2] = ["sculpture", "statue"];
+
+// Reads statues by extension: our scraped CSV, GeoJSON points, plain JSON arrays or an OSM XML
+// extract.
+pub fn load(path: impl AsRef<Path>) -> eyre::Result<Input> {
+    let path = path.as_ref();
+    let statues = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("geojson" | "json") => json(&fs::read_to_string(path)?)?,
+        Some("osm") => osm(BufReader::new(File::open(path)?))?,
+        Some("pbf") => {
+            return Err(eyre!(
+                "PBF extracts are not supported, convert {} to OSM XML first",
+                path.display()
+            ))
+        }
+        _ => {
+            return Ok(Input {
+                rows: csv(File::open(path)?)?,
+                fallbacks: false,
+                header: true,
+            })
+        }
+    };
+
+    Ok(Input {
+        rows: statues.into_iter().map(Ok).collect(),
+        fallbacks: true,
+        header: false,
+    })
+}
+
+// A row that doesn't fit the columns is kept with the reason, only failing to read the file
+// stops the load
+fn csv<R: Read>(reader: R) -> eyre::Result<Vec<Result<MaybeStatue, String>>> {
+    let mut rdr = csv::Reader::from_reader(reader);
+    let mut output = vec![];
+
+    for record in rdr.deserialize() {
+        let row = match record {
+            Ok(statue) => Ok(statue),
+            Err(err) if err.is_io_error() => return Err(err.into()),
+            Err(err) => Err(match err.kind() {
+                csv::ErrorKind::Deserialize { err, .. } => err.to_string(),
+                csv::ErrorKind::UnequalLengths {
+                    expected_len, len, ..
+                } => format!("{} fields instead of {}", len, expected_len),
+                _ => err.to_string(),
+            }),
+        };
+        output.push(row);
+    }
+
+    Ok(output)
+}
+
+// A GeoJSON feature collection, or a plain array of objects with the coordinates as properties
+fn json(text: &str) -> eyre::Result<Vec<MaybeStatue>> {
+    match serde_json::from_str(text)? {
+        Value::Array(items) => Ok(items
+            .iter()
+            .filter_map(Value::as_object)
+            .map(|item| {
+                let lat = number(item, &["lat", "latitude"]);
+                let lon = number(item, &["lon", "lng", "longitude"]);
+                normalize(&strings(item), lat, lon, None)
+            })
+            .collect()),
+        Value::Object(collection)
+            if collection.get("type") == Some(&"FeatureCollection".into()) =>
+        {
+            Ok(collection
+                .get("features")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(feature)
+                .collect())
+        }
+        _ => Err(eyre!(
+            "Expected a GeoJSON FeatureCollection or an array of statues"
+        )),
+    }
+}
+
+// Only points are read, anything else has no single place to walk to
+fn feature(feature: &Value) -> Option<MaybeStatue> {
+    let geometry = feature.get("geometry")?;
+    if geometry.get("type")? != "Point" {
+        return None;
+    }
+
+    let coordinates = geometry.get("coordinates")?.as_array()?;
+    let lon = coordinates.first()?.as_f64();
+    let lat = coordinates.get(1)?.as_f64();
+
+    let properties = feature
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(strings)
+        .unwrap_or_default();
+
+    Some(normalize(&properties, lat, lon, None))
+}
+
+// Artwork tagged as a sculpture, either as a node or as a way around it
+fn osm<R: BufRead>(reader: R) -> eyre::Result<Vec<MaybeStatue>> {
+    let mut reader = Reader::from_reader(reader);
+    let mut buf = vec![];
+
+    let mut coordinates: HashMap<i64, (f64, f64)> = HashMap::new();
+    let mut elements: Vec<Element> = vec![];
+    let mut element: Option<Element> = None;
+
+    loop {
+        let (start, empty) = match reader.read_event_into(&mut buf)? {
+            Event::Start(start) => (start, false),
+            Event::Empty(start) => (start, true),
+            Event::End(end) if matches!(end.name().as_ref(), b"node" | b"way") => {
+                elements.extend(element.take());
+                buf.clear();
+                continue;
+            }
+            Event::Eof => break,
+            _ => {
+                buf.clear();
+                continue;
+            }
+        };
+
+        match start.name().as_ref() {
+            name @ (b"node" | b"way") => {
+                let id: i64 = attribute(&start, b"id")?
+                    .ok_or_else(|| eyre!("OSM element without an id"))?
+                    .parse()?;
+                let position = match (attribute(&start, b"lat")?, attribute(&start, b"lon")?) {
+                    (Some(lat), Some(lon)) => Some((lat.parse()?, lon.parse()?)),
+                    _ => None,
+                };
+                if let Some(position) = position {
+                    coordinates.insert(id, position);
+                }
+
+                let found = Element {
+                    kind: if name == b"node" { "node" } else { "way" },
+                    id,
+                    position,
+                    refs: vec![],
+                    tags: HashMap::new(),
+                };
+                // Untagged nodes are empty elements, and never sculptures
+                if !empty {
+                    element = Some(found);
+                }
+            }
+            b"nd" => {
+                if let (Some(element), Some(node)) = (element.as_mut(), attribute(&start, b"ref")?)
+                {
+                    element.refs.push(node.parse()?);
+                }
+            }
+            b"tag" => {
+                if let (Some(element), Some(k), Some(v)) = (
+                    element.as_mut(),
+                    attribute(&start, b"k")?,
+                    attribute(&start, b"v")?,
+                ) {
+                    element.tags.insert(k, v);
+                }
+            }
+            _ => (),
+        }
+
+        buf.clear();
+    }
+
+    Ok(elements
+        .into_iter()
+        .filter(Element::is_sculpture)
+        .filter_map(|element| {
+            let (lat, lon) = element
+                .position
+                .or_else(|| centre(&element.refs, &coordinates))?;
+            let link = format!(
+                "https://www.openstreetmap.org/{}/{}",
+                element.kind, element.id
+            );
+            Some(normalize(&element.tags, Some(lat), Some(lon), Some(link)))
+        })
+        .collect())
+}
+
+// NOTE: A node or way from an OSM extract, with the tags needed to tell what it is
+struct Element {
+    kind: &'static str,
+    id: i64,
+    position: Option<(f64, f64)>,
+    refs: Vec<i64>,
+    tags: HashMap<String, String>,
+}
+
+impl Element {
+    fn is_sculpture(&self) -> bool {
+        self.tags.get("tourism").is_some_and(|v| v == "artwork")
+            && self
+                .tags
+                .get("artwork_type")
+                .is_some_and(|v| ARTWORK_TYPES.contains(&v.as_str()))
+    }
+}
+
+// Average of the nodes around an area, close enough for something the size of a statue
+fn centre(refs: &[i64], coordinates: &HashMap<i64, (f64, f64)>) -> Option<(f64, f64)> {
+    let known: Vec<_> = refs.iter().filter_map(|id| coordinates.get(id)).collect();
+    if known.is_empty() {
+        return None;
+    }
+
+    let n = known.len() as f64;
+    Some((
+        known.iter().map(|(lat, _)| lat).sum::<f64>() / n,
+        known.iter().map(|(_, lon)| lon).sum::<f64>() / n,
+    ))
+}
+
+// Our own column names win, then the OSM tags the same thing is usually found under
+fn normalize(
+    tags: &HashMap<String, String>,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    link: Option<String>,
+) -> MaybeStatue {
+    let first = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| tags.get(*key).filter(|value| !value.is_empty()))
+            .cloned()
+    };
+
+    let title = first(&["title", "name", "name:no", "name:nb"]);
+    let r#where = first(&["where", "addr:place"]).unwrap_or_default();
+    let link = first(&["link", "url", "website"])
+        .or_else(|| tags.get("wikipedia").and_then(|article| wikipedia(article)))
+        .or(link)
+        .unwrap_or_default();
+
+    let street = match (tags.get("addr:street"), tags.get("addr:housenumber")) {
+        (Some(street), Some(number)) => Some(format!("{} {}", street, number)),
+        (Some(street), None) => Some(street.clone()),
+        _ => None,
+    };
+    let address = first(&["address"]).or(street);
+
+    MaybeStatue::new(title, r#where, link, lat, lon, address)
+}
+
+// OSM refers to articles as `lang:Title`
+fn wikipedia(article: &str) -> Option<String> {
+    let (lang, title) = article.split_once(':')?;
+    Some(format!(
+        "https://{}.wikipedia.org/wiki/{}",
+        lang,
+        title.replace(' ', "_")
+    ))
+}
+
+// Properties as text, numbers included since some exports store house numbers as such
+fn strings(object: &Map<String, Value>) -> HashMap<String, String> {
+    object
+        .iter()
+        .filter_map(|(key, value)| match value {
+            Value::String(text) => Some((key.clone(), text.clone())),
+            Value::Number(number) => Some((key.clone(), number.to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+// Coordinates as numbers or as text
+fn number(object: &Map<String, Value>, keys: &[&str]) -> Option<f64> {
+    keys.iter()
+        .filter_map(|key| object.get(*key))
+        .find_map(|value| match value {
+            Value::Number(number) => number.as_f64(),
+            Value::String(text) => text.parse().ok(),
+            _ => None,
+        })
+}
+
+fn attribute(element: &BytesStart, key: &[u8]) -> eyre::Result<Option<String>> {
+    match element.try_get_attribute(key)? {
+        Some(attribute) => Ok(Some(attribute.unescape_value()?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{statue::Statue, validate::validate};
+
+    fn statues(found: Vec<MaybeStatue>) -> Vec<Statue> {
+        found
+            .into_iter()
+            .map(|statue| statue.with_fallbacks().try_into())
+            .collect::<eyre::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn reads_geojson_points() -> eyre::Result<()> {
+        let found = statues(json(
+            r#"{
+                "type": "FeatureCollection",
+                "features": [
+                    {
+                        "type": "Feature",
+                        "geometry": {"type": "Point", "coordinates": [10.7004, 59.9270]},
+                        "properties": {"name": "Sinnataggen", "addr:street": "Nobels gate", "addr:housenumber": 32}
+                    },
+                    {
+                        "type": "Feature",
+                        "geometry": {"type": "LineString", "coordinates": [[10.7, 59.9], [10.8, 59.9]]},
+                        "properties": {"name": "Not a statue"}
+                    }
+                ]
+            }"#,
+        )?);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].title(), "Sinnataggen");
+        assert_eq!(found[0].address(), "Nobels gate 32");
+        assert_eq!(found[0].latlon().lat(), 59.9270);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_json_arrays() -> eyre::Result<()> {
+        let found = json(
+            r#"[
+                {"title": "Tigern", "where": "Jernbanetorget", "link": "https://example.com/tigern", "lat": "59.9111", "lng": 10.7503},
+                {"title": "Somewhere", "where": "Unknown"}
+            ]"#,
+        )?;
+        assert_eq!(found.len(), 2);
+
+        let found = statues(found.into_iter().take(1).collect());
+        assert_eq!(found[0].r#where(), "Jernbanetorget");
+        assert_eq!(found[0].link(), "https://example.com/tigern");
+        // Addressed by the place it stands when there is no street
+        assert_eq!(found[0].address(), "Jernbanetorget");
+        assert_eq!(found[0].latlon().lon(), 10.7503);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_osm_sculptures() -> eyre::Result<()> {
+        let found = statues(osm(r#"<?xml version="1.0" encoding="UTF-8"?>
+            <osm version="0.6">
+              <node id="1" lat="59.9100" lon="10.7300">
+                <tag k="tourism" v="artwork"/>
+                <tag k="artwork_type" v="sculpture"/>
+                <tag k="name" v="Sjøfartsmonumentet"/>
+                <tag k="wikipedia" v="no:Sjøfartsmonumentet i Oslo"/>
+              </node>
+              <node id="2" lat="59.9200" lon="10.7400">
+                <tag k="tourism" v="artwork"/>
+                <tag k="artwork_type" v="mural"/>
+              </node>
+              <node id="3" lat="59.9000" lon="10.7000"/>
+              <node id="4" lat="59.9010" lon="10.7010"/>
+              <way id="5">
+                <nd ref="3"/>
+                <nd ref="4"/>
+                <tag k="tourism" v="artwork"/>
+                <tag k="artwork_type" v="statue"/>
+              </way>
+            </osm>"#
+            .as_bytes())?);
+
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].title(), "Sjøfartsmonumentet");
+        assert_eq!(
+            found[0].link(),
+            "https://no.wikipedia.org/wiki/Sjøfartsmonumentet_i_Oslo"
+        );
+
+        assert_eq!(found[1].title(), "Untitled sculpture");
+        assert_eq!(found[1].link(), "https://www.openstreetmap.org/way/5");
+        assert_eq!(found[1].address(), "59.90050, 10.70050");
+
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_unreadable_csv_rows() -> eyre::Result<()> {
+        let rows = csv("title,where,link,lat,lon,address
+Tigern,Jernbanetorget,a,59.9111,10.7503,Jernbanetorget 1
+Sinnataggen,Vigelandsparken,b,north,10.7004,Nobels gate 32
+Monolitten,Vigelandsparken,c,59.9270
+"
+        .as_bytes())?;
+
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].is_ok());
+        assert!(rows[1]
+            .as_ref()
+            .is_err_and(|reason| reason.contains("float")));
+        assert_eq!(rows[2].as_ref().err().unwrap(), "4 fields instead of 6");
+
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_untitled_csv_rows() -> eyre::Result<()> {
+        let input = Input {
+            rows: csv("title,where,link,lat,lon,address
+,Vigelandsparken,a,59.9270,10.7004,Nobels gate 32
+"
+            .as_bytes())?,
+            fallbacks: false,
+            header: true,
+        };
+
+        let findings = validate(&input);
+        assert_eq!(findings.len(), 1);
+        assert!(!findings[0].issue.rejects());
+
+        let found = input
+            .filled()?
+            .into_iter()
+            .map(|statue| statue.try_into())
+            .collect::<eyre::Result<Vec<Statue>>>()?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].title(), "Untitled sculpture");
+        assert_eq!(found[0].address(), "Nobels gate 32");
+
+        Ok(())
+    }
+}
@@ -1,31 +1,110 @@
 use eyre::eyre;
 use serde::Deserialize;
 
-use crate::{edge::Edge, latlon::LatLon};
+use crate::{
+    edge::{Cost, Edge, Spatial},
+    latlon::LatLon,
+};
+
+// Title of a sculpture nobody named
+const UNTITLED: &str = "Untitled sculpture";
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct MaybeStatue {
-    title: String,
+    title: Option<String>,
     r#where: String,
     link: String,
-    lat: Option<f32>,
-    lon: Option<f32>,
+    lat: Option<f64>,
+    lon: Option<f64>,
     address: Option<String>,
 }
 
+impl MaybeStatue {
+    pub fn new(
+        title: Option<String>,
+        r#where: String,
+        link: String,
+        lat: Option<f64>,
+        lon: Option<f64>,
+        address: Option<String>,
+    ) -> Self {
+        Self {
+            title,
+            r#where,
+            link,
+            lat,
+            lon,
+            address,
+        }
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn r#where(&self) -> &str {
+        &self.r#where
+    }
+
+    pub fn link(&self) -> &str {
+        &self.link
+    }
+
+    pub fn coordinates(&self) -> Option<(f64, f64)> {
+        self.lat.zip(self.lon)
+    }
+
+    // Fields a statue can't be placed without, empty if it can be
+    pub fn missing(&self) -> Vec<&'static str> {
+        [
+            ("title", self.title.is_none()),
+            ("lat", self.lat.is_none()),
+            ("lon", self.lon.is_none()),
+            ("address", self.address.is_none()),
+        ]
+        .into_iter()
+        .filter(|(_, missing)| *missing)
+        .map(|(field, _)| field)
+        .collect()
+    }
+
+    // Fills in what can be told from the rest: a statue without an address is addressed by where
+    // it stands or by its coordinates, and one without a title is an untitled sculpture
+    pub fn with_fallbacks(mut self) -> Self {
+        if self.address.is_none() {
+            self.address = if self.r#where.is_empty() {
+                self.coordinates()
+                    .map(|(lat, lon)| format!("{:.5}, {:.5}", lat, lon))
+            } else {
+                Some(self.r#where.clone())
+            };
+        }
+
+        self.with_title()
+    }
+
+    // Only fills in the title, a statue can be found without one
+    pub fn with_title(mut self) -> Self {
+        self.title.get_or_insert_with(|| UNTITLED.to_string());
+        self
+    }
+}
+
 impl TryInto<Statue> for MaybeStatue {
     type Error = eyre::Error;
 
     fn try_into(self) -> Result<Statue, Self::Error> {
-        if self.lat.is_none() || self.lon.is_none() || self.address.is_none() {
+        let missing = self.missing();
+        if !missing.is_empty() {
             return Err(eyre!(
-                "Missing coordinates and address for statue: {}",
-                self.title
+                "Missing {} for statue: {}",
+                missing.join(", "),
+                self.title.as_deref().unwrap_or_default()
             ));
         }
 
         Ok(Statue {
-            title: self.title,
+            title: self.title.unwrap(),
             r#where: self.r#where,
             link: self.link,
             lat: self.lat.unwrap(),
@@ -40,23 +119,86 @@ pub struct Statue {
     title: String,
     r#where: String,
     link: String,
-    lat: f32,
-    lon: f32,
+    lat: f64,
+    lon: f64,
     address: String,
 }
 
 impl Statue {
+    #[cfg(test)]
+    pub fn new(title: &str, r#where: &str, link: &str, lat: f64, lon: f64, address: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            r#where: r#where.to_string(),
+            link: link.to_string(),
+            lat,
+            lon,
+            address: address.to_string(),
+        }
+    }
+
     pub fn latlon(&self) -> LatLon {
         self.into()
     }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    // Where the statue stands, e.g. a park
+    pub fn r#where(&self) -> &str {
+        &self.r#where
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    // Page the statue was found on
+    pub fn link(&self) -> &str {
+        &self.link
+    }
+
+    // Whether the title or the place contains `search`, which has to be lowercase
+    pub fn matches(&self, search: &str) -> bool {
+        self.title.to_lowercase().contains(search) || self.r#where.to_lowercase().contains(search)
+    }
 }
 
 impl Edge for Statue {
-    fn weight(&self, node: &Self) -> f32 {
+    fn weight(&self, node: &Self) -> Cost {
         self.latlon().weight(&node.latlon())
     }
 }
 
+impl Spatial for Statue {
+    fn position(&self) -> [f64; 2] {
+        self.latlon().position()
+    }
+}
+
+// Grønland T-bane followed by Sinnataggen, the stops the exporters are tested with. The title
+// needs escaping in every markup format.
+#[cfg(test)]
+pub fn test_stops() -> Vec<crate::edge::NodeLatLon> {
+    use crate::{edge::NodeLatLon, latlon::StartingPoint};
+
+    vec![
+        NodeLatLon::StartingPoint(StartingPoint::new(
+            LatLon::new(59.9127, 10.7607),
+            "Grønland T-bane".to_string(),
+        )),
+        NodeLatLon::Statue(Statue::new(
+            "Sinnataggen & co",
+            "Vigelandsparken",
+            "https://example.com/sinnataggen",
+            59.927,
+            10.7004,
+            "Nobels gate 32",
+        )),
+    ]
+}
+
 impl From<Statue> for LatLon {
     fn from(val: Statue) -> Self {
         LatLon::new(val.lat, val.lon)
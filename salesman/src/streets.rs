@@ -0,0 +1,504 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap},
+    fmt::Debug,
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+    str::FromStr,
+};
+
+use eyre::eyre;
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+
+use crate::{
+    edge::{Cost, Spatial},
+    kdtree::KdTree,
+    latlon::LatLon,
+    pbf::{self, Element},
+    precomputed::Precomputed,
+};
+
+// Values of the highway tag a pedestrian can walk along. Motorways and trunk roads are left out
+// since walking is mostly forbidden there, unless the way explicitly allows it with foot=yes.
+const WALKABLE: [&str; 20] = [
+    "footway",
+    "path",
+    "pedestrian",
+    "steps",
+    "corridor",
+    "living_street",
+    "residential",
+    "service",
+    "unclassified",
+    "road",
+    "track",
+    "cycleway",
+    "bridleway",
+    "tertiary",
+    "tertiary_link",
+    "secondary",
+    "secondary_link",
+    "primary",
+    "primary_link",
+    "platform",
+];
+
+// Values of the highway tag a bike can ride along. Footways and pedestrian streets only count
+// with bicycle=yes, steps never do.
+const RIDEABLE: [&str; 15] = [
+    "cycleway",
+    "path",
+    "living_street",
+    "residential",
+    "service",
+    "unclassified",
+    "road",
+    "track",
+    "tertiary",
+    "tertiary_link",
+    "secondary",
+    "secondary_link",
+    "primary",
+    "primary_link",
+    "busway",
+];
+
+// How the network is travelled, which decides the ways that are part of it
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Mode {
+    #[default]
+    Walking,
+    Cycling,
+}
+
+impl FromStr for Mode {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "walking" => Ok(Self::Walking),
+            "cycling" => Ok(Self::Cycling),
+            _ => Err(eyre!("Unknown mode {}, expected walking or cycling", s)),
+        }
+    }
+}
+
+// Street graph built from an OpenStreetMap extract, used to replace straight-line distances
+// with the distance actually walked or cycled along streets and paths, e.g. around the fjord or
+// to the nearest bridge over the Akerselva.
+#[derive(Debug, Clone)]
+pub struct StreetNetwork {
+    positions: Vec<[f64; 2]>,
+    adjacency: Vec<Vec<(usize, f64)>>,
+    // Only covers the vertices in the largest connected part of the graph, so that every node
+    // snaps to somewhere it can walk to every other node from.
+    tree: KdTree,
+    vertices: Vec<usize>,
+}
+
+// NOTE: Used internally by Dijkstra, ordered by distance.
+#[derive(Debug, Clone, Copy)]
+struct Visit {
+    dist: f64,
+    vertex: usize,
+}
+
+impl PartialEq for Visit {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Visit {}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Visit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.dist
+            .total_cmp(&other.dist)
+            .then(self.vertex.cmp(&other.vertex))
+    }
+}
+
+// NOTE: Used internally while reading the extract, a way is kept if the tags allow the mode.
+#[derive(Debug, Default)]
+struct Way {
+    refs: Vec<i64>,
+    tags: HashMap<String, String>,
+}
+
+impl Way {
+    fn allows(&self, mode: Mode) -> bool {
+        let tag = |key: &str| self.tags.get(key).map(String::as_str);
+
+        let (key, highways) = match mode {
+            Mode::Walking => ("foot", WALKABLE.as_slice()),
+            Mode::Cycling => ("bicycle", RIDEABLE.as_slice()),
+        };
+
+        let allowed = matches!(tag(key), Some("yes" | "designated" | "permissive"));
+        if matches!(tag(key), Some("no")) {
+            return false;
+        }
+
+        match tag("highway") {
+            Some("steps") if mode == Mode::Cycling => false,
+            Some(highway) if highways.contains(&highway) => {
+                allowed || !matches!(tag("access"), Some("no" | "private"))
+            }
+            Some(_) => allowed,
+            None => false,
+        }
+    }
+}
+
+impl StreetNetwork {
+    // Reads an OSM extract, as PBF if the file ends in .pbf and as XML otherwise
+    pub fn load(path: impl AsRef<Path>, mode: Mode) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let reader = BufReader::new(File::open(path)?);
+
+        match path.extension().is_some_and(|ext| ext == "pbf") {
+            true => Self::parse_pbf(reader, mode),
+            false => Self::parse(reader, mode),
+        }
+    }
+
+    fn parse_pbf<R: Read>(reader: R, mode: Mode) -> eyre::Result<Self> {
+        let mut coordinates: HashMap<i64, LatLon> = HashMap::new();
+        let mut ways: Vec<Way> = vec![];
+
+        pbf::read(reader, |element| match element {
+            Element::Node { id, latlon } => {
+                coordinates.insert(id, latlon);
+            }
+            Element::Way { refs, tags } => {
+                let way = Way { refs, tags };
+                if way.allows(mode) {
+                    ways.push(way);
+                }
+            }
+        })?;
+
+        Self::build(&coordinates, &ways)
+    }
+
+    pub fn parse<R: BufRead>(reader: R, mode: Mode) -> eyre::Result<Self> {
+        let mut reader = Reader::from_reader(reader);
+        let mut buf = vec![];
+
+        let mut coordinates: HashMap<i64, LatLon> = HashMap::new();
+        let mut ways: Vec<Way> = vec![];
+        let mut way: Option<Way> = None;
+
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(element) | Event::Empty(element) => match element.name().as_ref() {
+                    b"node" => {
+                        let id = attribute(&element, b"id")?;
+                        let lat = attribute(&element, b"lat")?;
+                        let lon = attribute(&element, b"lon")?;
+
+                        if let (Some(id), Some(lat), Some(lon)) = (id, lat, lon) {
+                            coordinates
+                                .insert(id.parse()?, LatLon::new(lat.parse()?, lon.parse()?));
+                        }
+                    }
+                    b"way" => way = Some(Way::default()),
+                    b"nd" => {
+                        if let (Some(way), Some(node)) =
+                            (way.as_mut(), attribute(&element, b"ref")?)
+                        {
+                            way.refs.push(node.parse()?);
+                        }
+                    }
+                    b"tag" => {
+                        if let (Some(way), Some(k), Some(v)) = (
+                            way.as_mut(),
+                            attribute(&element, b"k")?,
+                            attribute(&element, b"v")?,
+                        ) {
+                            way.tags.insert(k, v);
+                        }
+                    }
+                    _ => (),
+                },
+                Event::End(element) if element.name().as_ref() == b"way" => {
+                    if let Some(way) = way.take().filter(|way| way.allows(mode)) {
+                        ways.push(way);
+                    }
+                }
+                Event::Eof => break,
+                _ => (),
+            }
+
+            buf.clear();
+        }
+
+        Self::build(&coordinates, &ways)
+    }
+
+    fn build(coordinates: &HashMap<i64, LatLon>, ways: &[Way]) -> eyre::Result<Self> {
+        let mut ids: HashMap<i64, usize> = HashMap::new();
+        let mut positions: Vec<[f64; 2]> = vec![];
+        let mut adjacency: Vec<Vec<(usize, f64)>> = vec![];
+
+        let mut vertex = |id: i64| -> Option<usize> {
+            let latlon = coordinates.get(&id)?;
+            Some(*ids.entry(id).or_insert_with(|| {
+                positions.push(latlon.position());
+                adjacency.push(vec![]);
+                positions.len() - 1
+            }))
+        };
+
+        let mut segments = vec![];
+        for way in ways {
+            // Nodes outside the extract are missing, which cuts the way at the boundary
+            let vertices: Vec<Option<usize>> = way.refs.iter().map(|id| vertex(*id)).collect();
+            for pair in vertices.windows(2) {
+                if let [Some(a), Some(b)] = pair {
+                    segments.push((*a, *b));
+                }
+            }
+        }
+
+        // Segments are short enough for the distance in the plane to be as good as haversine
+        for (a, b) in segments {
+            let length = distance(positions[a], positions[b]);
+            adjacency[a].push((b, length));
+            adjacency[b].push((a, length));
+        }
+
+        let vertices = largest_component(&adjacency);
+        if vertices.is_empty() {
+            return Err(eyre!("Found no usable ways in the street network"));
+        }
+
+        let tree = KdTree::new(vertices.iter().map(|v| positions[*v]).collect());
+
+        Ok(Self {
+            positions,
+            adjacency,
+            tree,
+            vertices,
+        })
+    }
+
+    // Closest vertex in the network and how far away it is
+    fn snap(&self, position: [f64; 2]) -> (usize, f64) {
+        let vertex = self.vertices[self.tree.nearest(position, 1)[0]];
+        (vertex, distance(position, self.positions[vertex]))
+    }
+
+    // Dijkstra from `source` until every target has been reached
+    fn shortest_paths(&self, source: usize, targets: &[usize]) -> HashMap<usize, f64> {
+        let mut dist = vec![f64::INFINITY; self.positions.len()];
+        let mut remaining: Vec<usize> = targets.to_vec();
+        let mut found = HashMap::new();
+
+        let mut heap = BinaryHeap::new();
+        dist[source] = 0.0;
+        heap.push(Reverse(Visit {
+            dist: 0.0,
+            vertex: source,
+        }));
+
+        while let Some(Reverse(Visit { dist: d, vertex })) = heap.pop() {
+            if d > dist[vertex] {
+                continue;
+            }
+
+            if remaining.contains(&vertex) {
+                remaining.retain(|target| *target != vertex);
+                found.insert(vertex, d);
+                if remaining.is_empty() {
+                    break;
+                }
+            }
+
+            for (next, length) in self.adjacency[vertex].iter() {
+                let next_dist = d + length;
+                if next_dist < dist[*next] {
+                    dist[*next] = next_dist;
+                    heap.push(Reverse(Visit {
+                        dist: next_dist,
+                        vertex: *next,
+                    }));
+                }
+            }
+        }
+
+        found
+    }
+
+    // Snaps every position to the network and calculates the distance between every pair through
+    // it, row by row. Includes the straight line from a position to where it joins the network.
+    pub fn distances(&self, positions: &[[f64; 2]]) -> Vec<Cost> {
+        let snapped: Vec<(usize, f64)> = positions
+            .iter()
+            .map(|position| self.snap(*position))
+            .collect();
+        let targets: Vec<usize> = snapped.iter().map(|(vertex, _)| *vertex).collect();
+
+        let n = positions.len();
+        let mut distances = vec![0.0; n * n];
+
+        for (i, (source, offset)) in snapped.iter().enumerate() {
+            let paths = self.shortest_paths(*source, &targets);
+
+            for (j, (target, target_offset)) in snapped.iter().enumerate() {
+                if i != j {
+                    distances[i * n + j] = offset + paths[target] + target_offset;
+                }
+            }
+        }
+
+        distances
+    }
+
+    // Wraps the nodes so that their weights are the distances through the network
+    pub fn route<N: Spatial>(&self, nodes: Vec<N>) -> Vec<Precomputed<N, true>> {
+        let positions: Vec<[f64; 2]> = nodes.iter().map(|node| node.position()).collect();
+        let distances = self.distances(&positions);
+
+        Precomputed::wrap(nodes, distances)
+    }
+}
+
+fn attribute(element: &BytesStart, key: &[u8]) -> eyre::Result<Option<String>> {
+    match element.try_get_attribute(key)? {
+        Some(attribute) => Ok(Some(attribute.unescape_value()?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+}
+
+// Vertices of the largest connected part of the graph
+fn largest_component(adjacency: &[Vec<(usize, f64)>]) -> Vec<usize> {
+    let mut seen = vec![false; adjacency.len()];
+    let mut largest = vec![];
+
+    for start in 0..adjacency.len() {
+        if seen[start] {
+            continue;
+        }
+
+        seen[start] = true;
+        let mut component = vec![start];
+        let mut stack = vec![start];
+
+        while let Some(vertex) = stack.pop() {
+            for (next, _) in adjacency[vertex].iter() {
+                if !seen[*next] {
+                    seen[*next] = true;
+                    component.push(*next);
+                    stack.push(*next);
+                }
+            }
+        }
+
+        if component.len() > largest.len() {
+            largest = component;
+        }
+    }
+
+    largest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edge::Edge;
+
+    // A river running north to south between lon 10.7600 and 10.7610, crossed by a single
+    // footbridge 500 m up north. The road along the river is closed to pedestrians.
+    const RIVER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <node id="1" lat="59.9100" lon="10.7600"/>
+  <node id="2" lat="59.9145" lon="10.7600"/>
+  <node id="3" lat="59.9145" lon="10.7610"/>
+  <node id="4" lat="59.9100" lon="10.7610"/>
+  <node id="5" lat="59.9000" lon="10.7600"/>
+  <node id="6" lat="59.9000" lon="10.7610"/>
+  <way id="10">
+    <nd ref="1"/><nd ref="2"/>
+    <tag k="highway" v="footway"/>
+  </way>
+  <way id="11">
+    <nd ref="2"/><nd ref="3"/>
+    <tag k="highway" v="footway"/>
+    <tag k="bridge" v="yes"/>
+  </way>
+  <way id="12">
+    <nd ref="3"/><nd ref="4"/>
+    <tag k="highway" v="residential"/>
+  </way>
+  <way id="13">
+    <nd ref="1"/><nd ref="5"/><nd ref="6"/><nd ref="4"/>
+    <tag k="highway" v="trunk"/>
+  </way>
+  <way id="14">
+    <nd ref="5"/><nd ref="6"/>
+    <tag k="highway" v="footway"/>
+    <tag k="foot" v="no"/>
+  </way>
+</osm>"#;
+
+    #[test]
+    fn walks_around_the_river() -> eyre::Result<()> {
+        let network = StreetNetwork::parse(RIVER.as_bytes(), Mode::Walking)?;
+
+        let west = LatLon::new(59.91, 10.76);
+        let east = LatLon::new(59.91, 10.761);
+        let straight = west.weight(&east);
+
+        let nodes = network.route(vec![west, east]);
+
+        // Up to the bridge, across and back down again
+        let walked = nodes[0].weight(&nodes[1]);
+        assert!((walked - (straight + 1000.0)).abs() < 5.0, "{}", walked);
+        assert_eq!(nodes[0].weight(&nodes[1]), nodes[1].weight(&nodes[0]));
+        assert_eq!(nodes[0].weight(&nodes[0]), 0.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn modes() {
+        let way = |tags: &[(&str, &str)]| Way {
+            refs: vec![],
+            tags: tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        };
+
+        let footway = way(&[("highway", "footway")]);
+        assert!(footway.allows(Mode::Walking));
+        assert!(!footway.allows(Mode::Cycling));
+
+        let shared = way(&[("highway", "footway"), ("bicycle", "yes")]);
+        assert!(shared.allows(Mode::Cycling));
+
+        let steps = way(&[("highway", "steps"), ("bicycle", "yes")]);
+        assert!(steps.allows(Mode::Walking));
+        assert!(!steps.allows(Mode::Cycling));
+
+        let cycleway = way(&[("highway", "cycleway"), ("foot", "no")]);
+        assert!(!cycleway.allows(Mode::Walking));
+        assert!(cycleway.allows(Mode::Cycling));
+    }
+}
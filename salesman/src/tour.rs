@@ -0,0 +1,161 @@
+// A closed tour as a permutation of node indices, the edge back from the last to the first
+// position is implied. Keeps the position of every node next to the order so that solvers
+// can jump from a node to where it is in the tour without searching for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tour {
+    order: Vec<usize>,
+    position: Vec<usize>,
+}
+
+impl Tour {
+    // `order` has to contain every index from 0 to order.len() exactly once
+    pub fn new(order: Vec<usize>) -> Self {
+        let mut position = vec![usize::MAX; order.len()];
+
+        for (p, node) in order.iter().enumerate() {
+            assert_eq!(
+                position[*node],
+                usize::MAX,
+                "node {} is visited twice",
+                node
+            );
+            position[*node] = p;
+        }
+
+        Self { order, position }
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    // Node at position p
+    pub fn node(&self, p: usize) -> usize {
+        self.order[p]
+    }
+
+    pub fn position(&self, node: usize) -> usize {
+        self.position[node]
+    }
+
+    // Position after p, wrapping around to the start
+    pub fn next(&self, p: usize) -> usize {
+        (p + 1) % self.order.len()
+    }
+
+    // Position before p, wrapping around to the end
+    pub fn prev(&self, p: usize) -> usize {
+        (p + self.order.len() - 1) % self.order.len()
+    }
+
+    // Reverses the nodes at positions i..=j
+    pub fn reverse(&mut self, i: usize, j: usize) {
+        let mut i = i;
+        let mut j = j;
+
+        while i < j {
+            self.order.swap(i, j);
+            self.position[self.order[i]] = i;
+            self.position[self.order[j]] = j;
+
+            i += 1;
+            j -= 1;
+        }
+    }
+
+    // Replaces edges tour[i]->tour[i+1] and tour[j]->tour[j+1]
+    // with tour[i]->tour[j] and tour[i+1]->tour[j+1]
+    pub fn swap_edges(&mut self, i: usize, j: usize) {
+        self.reverse(i + 1, j);
+    }
+
+    // Moves the l nodes starting at position s in between positions p and p + 1, reversing them
+    // if asked. The segment itself can not wrap around the end, and p can not be inside it or
+    // right before it.
+    pub fn move_segment(&mut self, s: usize, l: usize, p: usize, reversed: bool) {
+        let e = s + l - 1;
+
+        if p > e {
+            // Segment followed by s+l..=p, reverse both and then the whole range to swap them
+            let t = p - e;
+            self.reverse(s, p);
+            self.reverse(s, s + t - 1);
+            if !reversed {
+                self.reverse(s + t, p);
+            }
+        } else {
+            // p+1..s followed by the segment
+            let u = s - p - 1;
+            self.reverse(p + 1, e);
+            self.reverse(e - u + 1, e);
+            if !reversed {
+                self.reverse(p + 1, p + l);
+            }
+        }
+    }
+
+    // Nodes in visiting order, starting and ending at `start`
+    pub fn closed_from(&self, start: usize) -> Vec<usize> {
+        let n = self.order.len();
+        let p = self.position[start];
+
+        (0..=n).map(|offset| self.order[(p + offset) % n]).collect()
+    }
+}
+
+// Stops of an order of node indices, leaving out the return to the first stop of a round trip so
+// that every stop is only there once
+pub fn visits(order: &[usize]) -> &[usize] {
+    match order {
+        [first, .., last] if first == last => &order[..order.len() - 1],
+        _ => order,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_edges_keeps_positions() {
+        let mut tour = Tour::new((0..8).collect());
+
+        tour.swap_edges(0, 3);
+        assert_eq!(tour.closed_from(0), vec![0, 3, 2, 1, 4, 5, 6, 7, 0]);
+
+        tour.swap_edges(1, 4);
+        assert_eq!(tour.closed_from(0), vec![0, 3, 4, 1, 2, 5, 6, 7, 0]);
+
+        for node in 0..8 {
+            assert_eq!(tour.node(tour.position(node)), node);
+        }
+
+        assert_eq!(tour.closed_from(2), vec![2, 5, 6, 7, 0, 3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn move_segment() {
+        let mut tour = Tour::new((0..8).collect());
+        tour.move_segment(1, 2, 5, false);
+        assert_eq!(tour.closed_from(0), vec![0, 3, 4, 5, 1, 2, 6, 7, 0]);
+
+        let mut tour = Tour::new((0..8).collect());
+        tour.move_segment(5, 3, 1, true);
+        assert_eq!(tour.closed_from(0), vec![0, 1, 7, 6, 5, 2, 3, 4, 0]);
+
+        for node in 0..8 {
+            assert_eq!(tour.node(tour.position(node)), node);
+        }
+    }
+
+    #[test]
+    fn visits_every_stop_once() {
+        assert_eq!(visits(&[0, 2, 1, 0]), [0, 2, 1]);
+        assert_eq!(visits(&[0, 2, 1]), [0, 2, 1]);
+        assert_eq!(visits(&[0]), [0]);
+    }
+}
@@ -0,0 +1,593 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use eyre::eyre;
+use serde::Deserialize;
+use zip::ZipArchive;
+
+use crate::{
+    edge::{Cost, Edge, Geographic, Spatial},
+    kdtree::KdTree,
+    latlon::LatLon,
+    precomputed::Precomputed,
+    travel::TravelTime,
+};
+
+// Furthest we walk to or from a stop, in metres
+const MAX_ACCESS: f64 = 800.0;
+
+// Furthest we walk between two stops to change, in metres
+const MAX_TRANSFER: f64 = 300.0;
+
+// Number of closest stops looked at when searching within the distances above
+const NEARBY_STOPS: usize = 16;
+
+// NOTE: A vehicle going from one stop to the next, times in seconds since midnight.
+#[derive(Debug, Clone, Copy)]
+struct Connection {
+    departure: u32,
+    arrival: u32,
+    from: usize,
+    to: usize,
+    trip: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct StopRow {
+    stop_id: String,
+    stop_lat: f64,
+    stop_lon: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TripRow {
+    trip_id: String,
+    service_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct StopTimeRow {
+    trip_id: String,
+    arrival_time: Option<String>,
+    departure_time: Option<String>,
+    stop_id: String,
+    stop_sequence: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct CalendarRow {
+    service_id: String,
+    monday: u8,
+    tuesday: u8,
+    wednesday: u8,
+    thursday: u8,
+    friday: u8,
+    saturday: u8,
+    sunday: u8,
+    start_date: u32,
+    end_date: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct CalendarDateRow {
+    service_id: String,
+    date: u32,
+    exception_type: u8,
+}
+
+// NOTE: Where the GTFS files are read from, a zip as published or a directory it was extracted to.
+enum Feed {
+    Directory(PathBuf),
+    Zip(ZipArchive<File>),
+}
+
+impl Feed {
+    fn open(path: &Path) -> eyre::Result<Self> {
+        if path.is_dir() {
+            Ok(Feed::Directory(path.to_path_buf()))
+        } else {
+            Ok(Feed::Zip(ZipArchive::new(File::open(path)?)?))
+        }
+    }
+
+    // Rows of one of the files in the feed, or None if the feed does not have it
+    fn rows<T: for<'de> Deserialize<'de>>(&mut self, name: &str) -> eyre::Result<Option<Vec<T>>> {
+        let reader: Box<dyn Read + '_> = match self {
+            Feed::Directory(dir) => match File::open(dir.join(name)) {
+                Ok(file) => Box::new(file),
+                Err(_) => return Ok(None),
+            },
+            Feed::Zip(archive) => match archive.by_name(name) {
+                Ok(file) => Box::new(file),
+                Err(_) => return Ok(None),
+            },
+        };
+
+        let mut rows = vec![];
+        for record in csv::Reader::from_reader(reader).deserialize() {
+            rows.push(record?);
+        }
+
+        Ok(Some(rows))
+    }
+}
+
+// Public transport timetable from a GTFS feed, e.g. the one Ruter publishes for Oslo.
+// Used to find out when taking the T-bane or a tram between two nodes beats walking.
+#[derive(Debug, Clone)]
+pub struct Gtfs {
+    stops: Vec<LatLon>,
+    tree: KdTree,
+    // Sorted by departure, which is the order the connection scan needs them in
+    connections: Vec<Connection>,
+    trips: usize,
+    // Stops close enough to walk to when changing, with the distance in metres
+    transfers: Vec<Vec<(usize, f64)>>,
+}
+
+impl Gtfs {
+    // Reads the feed at `path`, only keeping the trips that run on `date` (YYYYMMDD). A feed
+    // covers weekdays, weekends and holidays alike, whose trips never all run on the same day.
+    pub fn load(path: impl AsRef<Path>, date: u32) -> eyre::Result<Self> {
+        if !is_date(date) {
+            return Err(eyre!("Expected a date like 20261019, got {}", date));
+        }
+
+        let mut feed = Feed::open(path.as_ref())?;
+
+        let stop_rows: Vec<StopRow> = feed
+            .rows("stops.txt")?
+            .ok_or_else(|| eyre!("GTFS feed has no stops.txt"))?;
+        let trip_rows: Vec<TripRow> = feed
+            .rows("trips.txt")?
+            .ok_or_else(|| eyre!("GTFS feed has no trips.txt"))?;
+        let stop_time_rows: Vec<StopTimeRow> = feed
+            .rows("stop_times.txt")?
+            .ok_or_else(|| eyre!("GTFS feed has no stop_times.txt"))?;
+
+        let services = active_services(
+            feed.rows("calendar.txt")?.unwrap_or_default(),
+            feed.rows("calendar_dates.txt")?.unwrap_or_default(),
+            date,
+        );
+
+        let mut stop_ids = HashMap::new();
+        let mut stops = vec![];
+        for row in stop_rows {
+            stop_ids.insert(row.stop_id, stops.len());
+            stops.push(LatLon::new(row.stop_lat, row.stop_lon));
+        }
+
+        let mut trip_ids = HashMap::new();
+        for row in trip_rows {
+            if services.contains(&row.service_id) {
+                let index = trip_ids.len();
+                trip_ids.insert(row.trip_id, index);
+            }
+        }
+
+        // Stops every trip makes in order, skipping stops without times
+        let mut calls: Vec<Vec<(u32, u32, u32, usize)>> = vec![vec![]; trip_ids.len()];
+        for row in stop_time_rows {
+            let (Some(trip), Some(stop)) = (trip_ids.get(&row.trip_id), stop_ids.get(&row.stop_id))
+            else {
+                continue;
+            };
+            let (Some(arrival), Some(departure)) = (row.arrival_time, row.departure_time) else {
+                continue;
+            };
+            if arrival.is_empty() || departure.is_empty() {
+                continue;
+            }
+
+            calls[*trip].push((
+                row.stop_sequence,
+                parse_time(&arrival)?,
+                parse_time(&departure)?,
+                *stop,
+            ));
+        }
+
+        let mut connections = vec![];
+        for (trip, calls) in calls.iter_mut().enumerate() {
+            calls.sort_by_key(|call| call.0);
+            for pair in calls.windows(2) {
+                let (_, _, departure, from) = pair[0];
+                let (_, arrival, _, to) = pair[1];
+                connections.push(Connection {
+                    departure,
+                    arrival,
+                    from,
+                    to,
+                    trip,
+                });
+            }
+        }
+        connections.sort_by_key(|connection| (connection.departure, connection.arrival));
+
+        let tree = KdTree::new(stops.iter().map(|stop| stop.position()).collect());
+
+        let mut gtfs = Self {
+            stops,
+            tree,
+            connections,
+            trips: trip_ids.len(),
+            transfers: vec![],
+        };
+
+        gtfs.transfers = (0..gtfs.stops.len())
+            .map(|stop| {
+                gtfs.nearby(&gtfs.stops[stop], MAX_TRANSFER)
+                    .into_iter()
+                    .filter(|(other, _)| *other != stop)
+                    .collect()
+            })
+            .collect();
+
+        Ok(gtfs)
+    }
+
+    // Stops within `max` metres and how far away they are
+    fn nearby(&self, latlon: &LatLon, max: f64) -> Vec<(usize, f64)> {
+        self.tree
+            .nearest(latlon.position(), NEARBY_STOPS)
+            .into_iter()
+            .map(|stop| (stop, latlon.calculate_distance_to(&self.stops[stop])))
+            .filter(|(_, distance)| *distance <= max)
+            .collect()
+    }
+
+    // Connection scan from one node: the earliest arrival at every stop when leaving at
+    // `departure`, walking to the stops around it first. Connections leaving more than `within`
+    // minutes later are not looked at.
+    fn earliest_arrivals(
+        &self,
+        access: &[(usize, f64)],
+        departure: u32,
+        within: Cost,
+        travel: TravelTime,
+    ) -> Vec<u32> {
+        let horizon = departure + (within * 60.0).ceil() as u32;
+        let mut earliest = vec![u32::MAX; self.stops.len()];
+        let mut boarded = vec![false; self.trips];
+
+        for (stop, distance) in access {
+            earliest[*stop] = earliest[*stop].min(departure + seconds(travel, *distance));
+        }
+
+        let first = self
+            .connections
+            .partition_point(|connection| connection.departure < departure);
+
+        for connection in self.connections[first..].iter() {
+            if connection.departure > horizon {
+                break;
+            }
+
+            if !boarded[connection.trip] && earliest[connection.from] > connection.departure {
+                continue;
+            }
+            boarded[connection.trip] = true;
+
+            if connection.arrival < earliest[connection.to] {
+                earliest[connection.to] = connection.arrival;
+
+                for (stop, distance) in self.transfers[connection.to].iter() {
+                    let arrival = connection.arrival + seconds(travel, *distance);
+                    earliest[*stop] = earliest[*stop].min(arrival);
+                }
+            }
+        }
+
+        earliest
+    }
+
+    // Travel time in minutes between every pair of nodes when leaving at `departure` (seconds
+    // since midnight), either walking the whole way or walking to a stop and riding from there.
+    // The tour is solved on these times, `legs` times it again for when it gets to every stop.
+    pub fn transit<N: Edge + Geographic>(
+        &self,
+        nodes: Vec<N>,
+        departure: u32,
+        travel: TravelTime,
+    ) -> Vec<Precomputed<N>> {
+        let n = nodes.len();
+
+        let access: Vec<Vec<(usize, f64)>> = nodes
+            .iter()
+            .map(|node| self.nearby(&node.latlon(), MAX_ACCESS))
+            .collect();
+
+        let mut minutes = vec![0.0; n * n];
+
+        for i in 0..n {
+            let walking: Vec<Cost> = (0..n)
+                .map(|j| travel.minutes(nodes[i].weight(&nodes[j]), 0.0))
+                .collect();
+
+            // Riding can not help once it is later than walking to the furthest node would be
+            let furthest = walking.iter().cloned().fold(0.0, Cost::max);
+            let earliest = self.earliest_arrivals(&access[i], departure, furthest, travel);
+
+            for j in 0..n {
+                if i == j {
+                    continue;
+                }
+
+                minutes[i * n + j] =
+                    fastest(walking[j], &earliest, &access[j], departure, travel).0;
+            }
+        }
+
+        Precomputed::wrap(nodes, minutes)
+    }
+
+    // Minutes of every leg of `order` and whether it is faster to ride, when the tour leaves the
+    // first node at `departure` (seconds since midnight). Unlike `transit`, every leg is looked up
+    // in the timetable for when the tour actually leaves the stop it starts at.
+    pub fn legs<N: Edge + Geographic>(
+        &self,
+        nodes: &[N],
+        order: &[usize],
+        departure: u32,
+        travel: TravelTime,
+    ) -> Vec<(Cost, bool)> {
+        let mut legs = vec![];
+
+        travel.schedule(order, |i, j, leaving| {
+            let departure = departure + (leaving * 60.0).round() as u32;
+            let walking = travel.minutes(nodes[i].weight(&nodes[j]), 0.0);
+
+            let access = self.nearby(&nodes[i].latlon(), MAX_ACCESS);
+            let earliest = self.earliest_arrivals(&access, departure, walking, travel);
+            let egress = self.nearby(&nodes[j].latlon(), MAX_ACCESS);
+
+            let leg = fastest(walking, &earliest, &egress, departure, travel);
+            legs.push(leg);
+            leg.0
+        });
+
+        legs
+    }
+}
+
+// Minutes to a node and whether that means riding, given the earliest arrival at every stop and
+// the stops around the node. Riding only wins if it is faster than `walking` there.
+fn fastest(
+    walking: Cost,
+    earliest: &[u32],
+    egress: &[(usize, f64)],
+    departure: u32,
+    travel: TravelTime,
+) -> (Cost, bool) {
+    let riding = egress
+        .iter()
+        .filter(|(stop, _)| earliest[*stop] != u32::MAX)
+        .map(|(stop, distance)| earliest[*stop] + seconds(travel, *distance))
+        .min()
+        .map(|arrival| (arrival - departure) as Cost / 60.0);
+
+    match riding {
+        Some(riding) if riding < walking => (riding, true),
+        _ => (walking, false),
+    }
+}
+
+// Seconds it takes to walk `metres`
+fn seconds(travel: TravelTime, metres: f64) -> u32 {
+    (travel.minutes(metres, 0.0) * 60.0).round() as u32
+}
+
+// Parses HH:MM or HH:MM:SS into seconds since midnight. GTFS allows hours past 24 for trips that
+// run past midnight.
+pub fn parse_time(time: &str) -> eyre::Result<u32> {
+    let parts: Vec<&str> = time.trim().split(':').collect();
+    if parts.len() != 2 && parts.len() != 3 {
+        return Err(eyre!("Expected a time like 10:30, got {}", time));
+    }
+
+    let mut seconds = 0;
+    for (part, unit) in parts.iter().zip([3600, 60, 1]) {
+        seconds += part.parse::<u32>()? * unit;
+    }
+
+    Ok(seconds)
+}
+
+// Services running on `date` (YYYYMMDD), from the weekly calendar and its exceptions
+fn active_services(
+    calendar: Vec<CalendarRow>,
+    calendar_dates: Vec<CalendarDateRow>,
+    date: u32,
+) -> HashSet<String> {
+    let weekday = weekday(date);
+
+    let mut services: HashSet<String> = calendar
+        .into_iter()
+        .filter(|row| row.start_date <= date && date <= row.end_date)
+        .filter(|row| {
+            let days = [
+                row.monday,
+                row.tuesday,
+                row.wednesday,
+                row.thursday,
+                row.friday,
+                row.saturday,
+                row.sunday,
+            ];
+            days[weekday] == 1
+        })
+        .map(|row| row.service_id)
+        .collect();
+
+    for row in calendar_dates.into_iter().filter(|row| row.date == date) {
+        match row.exception_type {
+            1 => {
+                services.insert(row.service_id);
+            }
+            2 => {
+                services.remove(&row.service_id);
+            }
+            _ => (),
+        }
+    }
+
+    services
+}
+
+// Whether a YYYYMMDD number is a day in the calendar, e.g. not the 30th of February
+fn is_date(date: u32) -> bool {
+    let (year, month, day) = (date / 10000, (date / 100) % 100, date % 100);
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return false,
+    };
+
+    year > 0 && (1..=days).contains(&day)
+}
+
+// Today as YYYYMMDD, in UTC since there is no time zone database to look up Oslo in
+pub fn today() -> u32 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+
+    date(seconds / 86400)
+}
+
+// YYYYMMDD of a number of days since 1970-01-01, using Howard Hinnant's civil_from_days
+fn date(days: u64) -> u32 {
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    (year * 10000 + month * 100 + day) as u32
+}
+
+// Day of the week of a YYYYMMDD date with Monday as 0, using Sakamoto's method
+fn weekday(date: u32) -> usize {
+    const OFFSETS: [u32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+
+    let (mut year, month, day) = (date / 10000, (date / 100) % 100, date % 100);
+    if month < 3 {
+        year -= 1;
+    }
+
+    // Sunday is 0 here
+    let sunday_first =
+        (year + year / 4 - year / 100 + year / 400 + OFFSETS[month as usize - 1] + day) % 7;
+    ((sunday_first + 6) % 7) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    // Two stops 5 km apart with a single train going east at 10:05, running on weekdays only
+    fn write_feed(dir: &Path) -> eyre::Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join("stops.txt"),
+            "stop_id,stop_name,stop_lat,stop_lon\nwest,West,59.91,10.70\neast,East,59.91,10.79\n",
+        )?;
+        fs::write(
+            dir.join("trips.txt"),
+            "route_id,service_id,trip_id\nline,weekdays,train\n",
+        )?;
+        fs::write(
+            dir.join("stop_times.txt"),
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence\ntrain,10:05:00,10:05:00,west,1\ntrain,10:15:00,10:15:00,east,2\n",
+        )?;
+        fs::write(
+            dir.join("calendar.txt"),
+            "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\nweekdays,1,1,1,1,1,0,0,20260101,20261231\n",
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn rides_when_faster() -> eyre::Result<()> {
+        let dir = std::env::temp_dir().join(format!("salesman-gtfs-{}", std::process::id()));
+        write_feed(&dir)?;
+
+        let nodes = vec![LatLon::new(59.91, 10.701), LatLon::new(59.91, 10.789)];
+        let travel = TravelTime::default();
+
+        // Monday
+        let gtfs = Gtfs::load(&dir, 20261019)?;
+        let transit = gtfs.transit(nodes.clone(), parse_time("10:00")?, travel);
+
+        assert!(transit[0].weight(&transit[1]) < 20.0);
+        assert!(transit[1].weight(&transit[0]) > 60.0);
+
+        // Too late for the train
+        let transit = gtfs.transit(nodes.clone(), parse_time("10:06")?, travel);
+        assert!(transit[0].weight(&transit[1]) > 60.0);
+
+        // Sunday
+        let gtfs = Gtfs::load(&dir, 20261018)?;
+        let transit = gtfs.transit(nodes.clone(), parse_time("10:00")?, travel);
+        assert!(transit[0].weight(&transit[1]) > 60.0);
+
+        // Walking east first gets back west long after the train has left
+        let gtfs = Gtfs::load(&dir, 20261019)?;
+        let legs = gtfs.legs(&nodes, &[0, 1], parse_time("10:00")?, travel);
+        assert!(legs[0].1 && legs[0].0 < 20.0);
+        let legs = gtfs.legs(&nodes, &[1, 0, 1], parse_time("10:00")?, travel);
+        assert!(legs
+            .iter()
+            .all(|(minutes, rides)| !rides && *minutes > 60.0));
+
+        fs::remove_dir_all(dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn weekdays() {
+        assert_eq!(weekday(20261019), 0);
+        assert_eq!(weekday(20240229), 3);
+        assert_eq!(weekday(20261018), 6);
+    }
+
+    #[test]
+    fn dates() {
+        assert!(is_date(20261019));
+        assert!(is_date(20240229));
+        assert!(!is_date(20260229));
+        assert!(!is_date(20261301));
+        assert!(!is_date(2026000));
+        assert!(!is_date(20261032));
+
+        assert_eq!(date(0), 19700101);
+        assert_eq!(date(20745), 20261019);
+        assert_eq!(date(19782), 20240229);
+        assert!(is_date(today()));
+        assert!(Gtfs::load("does-not-matter", 20261301).is_err());
+    }
+}
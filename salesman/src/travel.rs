@@ -0,0 +1,263 @@
+use std::{fmt::Debug, path::Path};
+
+use serde::Deserialize;
+
+use crate::{
+    edge::{Cost, Edge, Geographic, Spatial},
+    kdtree::KdTree,
+    latlon::LatLon,
+    precomputed::Precomputed,
+};
+
+// A relaxed city pace in km/h, leaving time to look around
+const WALKING_SPEED: f64 = 4.5;
+
+// Naismith's rule: one extra minute for every ten metres of climb
+const CLIMB_PENALTY: f64 = 0.1;
+
+// Minutes spent looking at every statue
+const DWELL: f64 = 5.0;
+
+// City bikes are heavy, so a relaxed pace in km/h
+const CYCLING_SPEED: f64 = 15.0;
+
+// Pushing a bike up a metre takes about twice as long as walking it
+const CYCLING_CLIMB_PENALTY: f64 = 0.2;
+
+// Number of elevation samples blended together for a single node
+const ELEVATION_SAMPLES: usize = 4;
+
+// Turns distances into minutes. Climbing costs extra on top of the walk itself, going downhill is
+// free, which makes the time from a to b differ from the time from b to a.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TravelTime {
+    // km/h
+    speed: f64,
+    // Minutes per metre of climb
+    climb_penalty: f64,
+    // Minutes at every stop except the starting point
+    dwell: f64,
+}
+
+impl Default for TravelTime {
+    fn default() -> Self {
+        Self {
+            speed: WALKING_SPEED,
+            climb_penalty: CLIMB_PENALTY,
+            dwell: DWELL,
+        }
+    }
+}
+
+// NOTE: Arrival and departure at one stop of a tour, in minutes since leaving the start.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stop {
+    pub node: usize,
+    pub arrival: Cost,
+    pub departure: Cost,
+}
+
+impl TravelTime {
+    // Same as the default walking profile, but on a bike
+    pub fn cycling() -> Self {
+        Self {
+            speed: CYCLING_SPEED,
+            climb_penalty: CYCLING_CLIMB_PENALTY,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn with_climb_penalty(mut self, climb_penalty: f64) -> Self {
+        self.climb_penalty = climb_penalty;
+        self
+    }
+
+    pub fn with_dwell(mut self, dwell: f64) -> Self {
+        self.dwell = dwell;
+        self
+    }
+
+    // Minutes to walk `metres` while climbing `climb` metres
+    pub fn minutes(&self, metres: Cost, climb: f64) -> Cost {
+        metres / (self.speed * 1000.0 / 60.0) + climb.max(0.0) * self.climb_penalty
+    }
+
+    // Wraps the nodes so that their weights are in minutes, looking up the elevation of every node
+    // if there is an elevation model. Without one the ground is treated as flat.
+    pub fn timed<N: Edge + Geographic>(
+        &self,
+        nodes: Vec<N>,
+        elevation: Option<&Elevation>,
+    ) -> Vec<Precomputed<N>> {
+        let heights = nodes
+            .iter()
+            .map(|node| elevation.map_or(0.0, |elevation| elevation.at(node.latlon().position())))
+            .collect();
+
+        self.climbing(nodes, heights)
+    }
+
+    // Wraps the nodes so that their weights are the minutes it takes to walk their weight in
+    // metres, from one height in metres above sea level to another
+    fn climbing<N: Edge>(&self, nodes: Vec<N>, heights: Vec<f64>) -> Vec<Precomputed<N>> {
+        Precomputed::calculate(nodes, |nodes, i, j| {
+            self.minutes(nodes[i].weight(&nodes[j]), heights[j] - heights[i])
+        })
+    }
+
+    // When every stop in `order` is reached and left. The order is closed, so the first and last
+    // node are the starting point, which is the only place without a dwell time. `weight` is given
+    // the minutes since the start when the leg sets off.
+    pub fn schedule(
+        &self,
+        order: &[usize],
+        mut weight: impl FnMut(usize, usize, Cost) -> Cost,
+    ) -> Vec<Stop> {
+        let mut stops: Vec<Stop> = vec![];
+
+        for (p, node) in order.iter().enumerate() {
+            let stop = match stops.last() {
+                None => Stop {
+                    node: *node,
+                    arrival: 0.0,
+                    departure: 0.0,
+                },
+                Some(previous) => {
+                    let arrival =
+                        previous.departure + weight(previous.node, *node, previous.departure);
+                    let dwell = if p + 1 < order.len() { self.dwell } else { 0.0 };
+
+                    Stop {
+                        node: *node,
+                        arrival,
+                        departure: arrival + dwell,
+                    }
+                }
+            };
+
+            stops.push(stop);
+        }
+
+        stops
+    }
+}
+
+// NOTE: One row in an elevation CSV, e.g. exported from a DEM as points.
+#[derive(Debug, Deserialize)]
+struct ElevationSample {
+    lat: f64,
+    lon: f64,
+    elevation: f64,
+}
+
+// Elevation model from points with a known height, looked up by blending the closest samples.
+#[derive(Debug, Clone)]
+pub struct Elevation {
+    positions: Vec<[f64; 2]>,
+    heights: Vec<f64>,
+    tree: KdTree,
+}
+
+impl Elevation {
+    // Reads a CSV with lat, lon and elevation columns, elevation in metres
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let mut rdr = csv::Reader::from_path(path)?;
+
+        let mut positions = vec![];
+        let mut heights = vec![];
+        for record in rdr.deserialize() {
+            let sample: ElevationSample = record?;
+            positions.push(LatLon::new(sample.lat, sample.lon).position());
+            heights.push(sample.elevation);
+        }
+
+        Ok(Self::new(positions, heights))
+    }
+
+    fn new(positions: Vec<[f64; 2]>, heights: Vec<f64>) -> Self {
+        Self {
+            tree: KdTree::new(positions.clone()),
+            positions,
+            heights,
+        }
+    }
+
+    // Inverse distance weighting of the closest samples
+    pub fn at(&self, position: [f64; 2]) -> f64 {
+        let mut weights = 0.0;
+        let mut height = 0.0;
+
+        for i in self.tree.nearest(position, ELEVATION_SAMPLES) {
+            let [x, y] = self.positions[i];
+            let dist = ((x - position[0]).powi(2) + (y - position[1]).powi(2)).sqrt();
+            if dist < 1e-6 {
+                return self.heights[i];
+            }
+
+            weights += 1.0 / dist;
+            height += self.heights[i] / dist;
+        }
+
+        if weights == 0.0 {
+            return 0.0;
+        }
+
+        height / weights
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone)]
+    struct OneDPoint {
+        x: f64,
+    }
+
+    impl Edge for OneDPoint {
+        fn weight(&self, node: &Self) -> Cost {
+            (self.x - node.x).abs()
+        }
+    }
+
+    #[test]
+    fn climbing_takes_longer() {
+        let travel = TravelTime::default().with_speed(6.0);
+        let nodes = vec![OneDPoint { x: 0.0 }, OneDPoint { x: 1000.0 }];
+        let timed = travel.climbing(nodes, vec![0.0, 20.0]);
+
+        // 1 km at 6 km/h is ten minutes, plus two for the climb
+        assert!((timed[0].weight(&timed[1]) - 12.0).abs() < 1e-9);
+        assert!((timed[1].weight(&timed[0]) - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn schedule_dwells_at_statues() {
+        let travel = TravelTime::default().with_dwell(5.0);
+        let stops = travel.schedule(&[0, 2, 1, 0], |_, _, _| 10.0);
+
+        let times: Vec<(Cost, Cost)> = stops
+            .iter()
+            .map(|stop| (stop.arrival, stop.departure))
+            .collect();
+        assert_eq!(
+            times,
+            vec![(0.0, 0.0), (10.0, 15.0), (25.0, 30.0), (40.0, 40.0)]
+        );
+    }
+
+    #[test]
+    fn elevation_blends_samples() {
+        let elevation = Elevation::new(vec![[0.0, 0.0], [10.0, 0.0]], vec![0.0, 10.0]);
+
+        assert_eq!(elevation.at([0.0, 0.0]), 0.0);
+        assert!((elevation.at([5.0, 0.0]) - 5.0).abs() < 1e-9);
+        assert!((elevation.at([7.5, 0.0]) - 7.5).abs() < 1e-9);
+    }
+}
@@ -1,180 +1,193 @@
 use std::fmt::Debug;
 
-use crate::{edge::Edge, tsp_ext::three_opt::ThreeOpt};
-
+use crate::{
+    edge::{Cost, Edge, Spatial},
+    matrix::DistanceMatrix,
+    pipeline::{Pipeline, Stage},
+    tour::Tour,
+    tsp_ext::{candidates::Candidates, local_search::Strategy, solver::Problem},
+};
+
+// The solvers work on a tour of node indices, nodes are only looked up again in `path`.
+#[allow(clippy::upper_case_acronyms)]
 pub struct TSP<E: Edge + Clone> {
     nodes: Vec<E>,
-    path: Vec<E>,
+    tour: Tour,
+    candidates: Option<Candidates>,
+    // Weights between nodes, filled lazily unless a calculated one is given through `with_matrix`
+    matrix: DistanceMatrix,
+    strategy: Strategy,
+    seed: Option<u64>,
 }
 
 impl<E: Edge + Clone + Debug> TSP<E> {
     pub fn new(nodes: Vec<E>) -> Self {
         Self {
+            matrix: DistanceMatrix::lazy(nodes.len()),
             nodes,
-            path: vec![],
+            tour: Tour::new(vec![]),
+            candidates: None,
+            strategy: Strategy::default(),
+            seed: None,
         }
     }
 
+    // Starts from the nodes in the order they are given, so that solvers can be tested on a known tour
+    #[cfg(test)]
     pub fn new_and_initialize_path(nodes: Vec<E>) -> Self {
         Self {
-            nodes: nodes.clone(),
-            path: nodes,
+            matrix: DistanceMatrix::lazy(nodes.len()),
+            tour: Tour::new((0..nodes.len()).collect()),
+            nodes,
+            candidates: None,
+            strategy: Strategy::default(),
+            seed: None,
         }
     }
 
-    pub fn path(&self) -> &Vec<E> {
-        &self.path
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
     }
 
-    fn distance_matrix(&self) -> Vec<Vec<f32>> {
-        let n = self.nodes.len();
-
-        let mut m: Vec<Vec<f32>> = vec![vec![0.0; n]; n];
-
-        for i in 0..n {
-            for j in 0..n {
-                m[i][j] = self.nodes[i].weight(&self.nodes[j]);
-            }
-        }
-
-        m
+    // Seeds the random choices of simulated annealing, so that a run can be repeated
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
     }
 
-    // Replaces edges path[i]->path[i+1] and path[j]->path[j+1]
-    // with path[i]->path[j] and path[i+1]->path[j+1]
-    fn swap_edges(&mut self, i: usize, j: usize) {
-        let mut i = i;
-        let mut j = j;
-
-        i += 1;
+    // Uses an already calculated matrix, e.g. one loaded from disk, instead of filling a new one.
+    pub fn with_matrix(mut self, matrix: DistanceMatrix) -> Self {
+        assert_eq!(matrix.size(), self.nodes.len());
+        self.matrix = matrix;
+        self
+    }
 
-        while i < j {
-            let tmp = self.path[i].clone();
-            self.path[i] = self.path[j].clone();
-            self.path[j] = tmp;
+    // Nodes in visiting order, starting and ending at the first node.
+    #[cfg(test)]
+    pub fn path(&self) -> Vec<E> {
+        self.order()
+            .into_iter()
+            .map(|i| self.nodes[i].clone())
+            .collect()
+    }
 
-            i += 1;
-            j -= 1;
+    // Same as `path`, but the indices of the nodes instead of the nodes themselves.
+    pub fn order(&self) -> Vec<usize> {
+        if self.tour.is_empty() {
+            return vec![];
         }
-    }
 
-    fn dist(&self, index_1: usize, index_2: usize) -> f32 {
-        let path = &self.path;
-        path[index_1].weight(&path[index_2])
+        self.tour.closed_from(0)
     }
 
-    // NOTE: Needs a path to optimize
-    pub fn two_opt(&mut self) -> Vec<E> {
-        let n = self.path().len();
+    // Cost of every leg of the tour, in the same order as `order`
+    pub fn legs(&self) -> Vec<Cost> {
+        let problem = self.problem();
 
-        // Total cost of path
-        let mut cost = self.calculate_path_cost();
-        let mut found_improvement = true;
+        self.order()
+            .windows(2)
+            .map(|pair| problem.weight(pair[0], pair[1]))
+            .collect()
+    }
 
-        while found_improvement {
-            found_improvement = false;
+    #[cfg(test)]
+    fn swap_edges(&mut self, i: usize, j: usize) {
+        self.tour.swap_edges(i, j);
+    }
 
-            for i in 0..(n - 1) {
-                let a = i;
-                let b = (i + 1) % n;
-                for j in (i + 2)..n {
-                    let c = j;
-                    let d = (j + 1) % n;
-                    // Calculate delta change if connections are switched
-                    let length_delta =
-                        -self.dist(a, b) - self.dist(c, d) + self.dist(a, c) + self.dist(b, d);
+    // What the solvers get to work with, borrowed from this `TSP`
+    fn problem(&self) -> Problem<'_, E> {
+        let problem = Problem::new(&self.nodes, &self.matrix)
+            .with_strategy(self.strategy)
+            .with_seed(self.seed);
 
-                    if length_delta < -0.001 {
-                        self.swap_edges(a, c);
-                        cost += length_delta;
-                        found_improvement = true;
-                    }
-                }
-            }
+        match &self.candidates {
+            Some(candidates) => problem.with_candidates(candidates),
+            None => problem,
         }
-
-        self.path().clone()
     }
 
-    pub fn three_opt(&mut self) -> Vec<E> {
-        self.path = ThreeOpt::new(self.nodes.clone(), self.path.clone()).solve();
-        self.path.clone()
+    // Runs every stage of the pipeline, returning the cost after each of them
+    pub fn run(&mut self, pipeline: &Pipeline<E>) -> Vec<Stage> {
+        let (tour, stages) = pipeline.run(&self.problem());
+        self.tour = tour;
+        stages
     }
 
-    pub fn three_opt_sm(&mut self) -> Vec<E> {
-        self.path = ThreeOpt::new(self.nodes.clone(), self.path.clone()).solve_sm();
-        self.path.clone()
+    pub fn calculate_path_cost(&self) -> Cost {
+        self.problem().cost(&self.tour)
     }
+}
 
-    // Nearest neighbour
-    // Currently assumes first node is the starting point, but could start at random point.
-    pub fn nn(&mut self) -> Vec<E> {
-        let mut path: Vec<E> = vec![];
-
-        let mut nodes_to_visit = self.nodes.clone();
-
-        let initial = nodes_to_visit.remove(0);
-        let mut current_node = initial.clone();
-        path.push(initial);
-
-        while !nodes_to_visit.is_empty() {
-            let mut current_shortest = f32::MAX;
-            let mut current_index: usize = usize::MIN;
+impl<E: Edge + Spatial + Clone + Debug> TSP<E> {
+    // Restricts the solvers to the k nearest neighbours of every node, found through a k-d tree.
+    pub fn with_candidates(mut self, k: usize) -> Self {
+        self.candidates = Some(Candidates::new(&self.nodes, k));
+        self
+    }
+}
 
-            for (i, node) in nodes_to_visit.iter().enumerate() {
-                let weight = current_node.weight(node);
+#[cfg(test)]
+mod tests {
 
-                if weight < current_shortest {
-                    current_shortest = weight;
-                    current_index = i;
-                }
-            }
+    use super::*;
+    use crate::{
+        edge::TOLERANCE,
+        tsp_ext::{
+            nn::NearestNeighbour,
+            or_opt::OrOpt,
+            solver::{Constructor, Improver},
+            three_opt::{SimulatedAnnealing, ThreeOpt},
+            two_opt::TwoOpt,
+        },
+    };
+    use std::fs;
 
-            let chosen = nodes_to_visit.remove(current_index);
-            current_node = chosen.clone();
-            path.push(chosen);
+    // One solver at a time, instead of building a pipeline for every test
+    impl<E: Edge + Clone + Debug> TSP<E> {
+        fn construct(&mut self, constructor: &dyn Constructor<E>) {
+            self.tour = constructor.construct(&self.problem());
         }
 
-        path.push(path[0].clone());
+        fn improve(&mut self, improver: &dyn Improver<E>) {
+            self.tour = improver.improve(&self.problem(), self.tour.clone());
+        }
 
-        self.path = path.clone();
+        fn nn(&mut self) {
+            self.construct(&NearestNeighbour);
+        }
 
-        path
-    }
+        fn two_opt(&mut self) {
+            self.improve(&TwoOpt);
+        }
 
-    pub fn calculate_path_cost(&self) -> f32 {
-        let path = &self.path;
-        let n = path.len();
-        let mut cost = self.dist(path.len() - 1, 0);
+        fn or_opt(&mut self) {
+            self.improve(&OrOpt);
+        }
 
-        for i in 0..n - 1 {
-            let dist = self.dist(i, i + 1);
-            cost += dist;
+        fn three_opt(&mut self) {
+            self.improve(&ThreeOpt);
         }
 
-        cost
+        fn three_opt_sm(&mut self) {
+            self.improve(&SimulatedAnnealing);
+        }
     }
-}
-
-#[cfg(test)]
-mod tests {
-
-    use super::*;
-    use std::fs;
 
     #[derive(Clone, Debug, PartialEq)]
     struct OneDPoint {
-        x: f32,
+        x: f64,
     }
 
     impl OneDPoint {
-        fn new(x: f32) -> Self {
+        fn new(x: f64) -> Self {
             Self { x }
         }
     }
 
     impl Edge for OneDPoint {
-        fn weight(&self, node: &Self) -> f32 {
+        fn weight(&self, node: &Self) -> Cost {
             let diffx = self.x - node.x;
 
             (diffx * diffx).sqrt()
@@ -183,20 +196,20 @@ mod tests {
 
     #[derive(Clone, Debug, PartialEq)]
     struct Point {
-        x: f32,
-        y: f32,
+        x: f64,
+        y: f64,
 
         // This number indicates where in the path this node should appear in calculated path
         index: i32,
     }
 
     impl Point {
-        pub fn new(x: f32, y: f32, index: i32) -> Self {
+        pub fn new(x: f64, y: f64, index: i32) -> Self {
             Self { x, y, index }
         }
 
         // Distance between two points
-        fn distance_to(&self, other: &Point) -> f32 {
+        fn distance_to(&self, other: &Point) -> f64 {
             let diffx = self.x - other.x;
             let diffy = self.y - other.y;
 
@@ -205,11 +218,42 @@ mod tests {
     }
 
     impl Edge for Point {
-        fn weight(&self, point: &Self) -> f32 {
+        fn weight(&self, point: &Self) -> Cost {
             self.distance_to(point)
         }
     }
 
+    impl Spatial for Point {
+        fn position(&self) -> [f64; 2] {
+            [self.x, self.y]
+        }
+    }
+
+    // Climbing costs three times as much as walking on the flat or downhill
+    #[derive(Clone, Debug, PartialEq)]
+    struct Hill {
+        x: f64,
+        y: f64,
+    }
+
+    impl Edge for Hill {
+        const SYMMETRIC: bool = false;
+
+        fn weight(&self, node: &Self) -> Cost {
+            let diffx = self.x - node.x;
+            let diffy = self.y - node.y;
+            let climb = (node.y - self.y).max(0.0);
+
+            ((diffx * diffx) + (diffy * diffy)).sqrt() + 2.0 * climb
+        }
+    }
+
+    impl Spatial for Hill {
+        fn position(&self) -> [f64; 2] {
+            [self.x, self.y]
+        }
+    }
+
     struct WikiPaths {
         path_1: Vec<Point>,
         path_2: Vec<Point>,
@@ -224,8 +268,8 @@ mod tests {
             .filter_map(|(index, line)| {
                 let mut line = line.splitn(2, ",");
 
-                let x = line.next().and_then(|v| v.parse::<f32>().ok())?;
-                let y = line.next().and_then(|v| v.parse::<f32>().ok())?;
+                let x = line.next().and_then(|v| v.parse::<f64>().ok())?;
+                let y = line.next().and_then(|v| v.parse::<f64>().ok())?;
 
                 Some(Point::new(x, y, index as i32))
             })
@@ -238,8 +282,8 @@ mod tests {
             .filter_map(|(index, line)| {
                 let mut line = line.splitn(2, ",");
 
-                let x = line.next().and_then(|v| v.parse::<f32>().ok())?;
-                let y = line.next().and_then(|v| v.parse::<f32>().ok())?;
+                let x = line.next().and_then(|v| v.parse::<f64>().ok())?;
+                let y = line.next().and_then(|v| v.parse::<f64>().ok())?;
 
                 Some(Point::new(x, y, index as i32))
             })
@@ -261,7 +305,7 @@ mod tests {
         let tsp = TSP::new_and_initialize_path(nodes);
         let cost = tsp.calculate_path_cost();
 
-        assert_eq!(cost, 14.142134)
+        assert!((cost - 10.0 * 2.0_f64.sqrt()).abs() < 1e-9, "{}", cost)
     }
 
     #[test]
@@ -276,7 +320,8 @@ mod tests {
         let nodes = vec![node_1, node_2, node_3, node_4, node_5, node_6];
 
         let mut tsp = TSP::new(nodes);
-        let path = tsp.nn();
+        tsp.nn();
+        let path = tsp.path();
 
         let indexed_path: Vec<i32> = path.iter().map(|node| node.index).collect();
 
@@ -284,7 +329,7 @@ mod tests {
 
         let cost = tsp.calculate_path_cost();
 
-        assert_eq!(cost, 22.0);
+        assert!((cost - 23.509428).abs() < 1e-6, "{}", cost);
     }
 
     #[test]
@@ -295,21 +340,23 @@ mod tests {
         let cost = tsp.calculate_path_cost();
         assert_eq!(cost.floor(), 55723.0);
 
-        let _ = tsp.two_opt();
+        tsp.two_opt();
 
         let cost = tsp.calculate_path_cost();
 
-        assert_eq!(cost.floor(), 8559.0);
+        // Lower than the 8559 of the plain sweep, since the don't-look queue tries moves in another order
+        assert_eq!(cost.floor(), 8328.0);
 
         let mut tsp2 = TSP::new_and_initialize_path(path_1);
         let cost = tsp2.calculate_path_cost();
         assert_eq!(cost.floor(), 8586.0);
 
-        let _ = tsp2.two_opt();
+        tsp2.two_opt();
 
-        let cost = tsp.calculate_path_cost();
+        let cost = tsp2.calculate_path_cost();
 
-        assert_eq!(cost.floor(), 8559.0);
+        // Already a 2-opt local optimum, there is no pair of edges left to swap
+        assert_eq!(cost.floor(), 8586.0);
 
         Ok(())
     }
@@ -323,7 +370,7 @@ mod tests {
         let prev_cost = tsp.calculate_path_cost();
         // assert_eq!(prev_cost.floor(), 55723.0);
 
-        let _ = tsp.three_opt();
+        tsp.three_opt();
 
         let cost = tsp.calculate_path_cost();
 
@@ -338,7 +385,7 @@ mod tests {
 
         let prev_cost = tsp2.calculate_path_cost();
 
-        let _ = tsp2.three_opt();
+        tsp2.three_opt();
 
         let cost = tsp2.calculate_path_cost();
         assert!(cost < prev_cost);
@@ -357,7 +404,7 @@ mod tests {
         let prev_cost = tsp.calculate_path_cost();
         // assert_eq!(prev_cost.floor(), 55723.0);
 
-        let _ = tsp.three_opt_sm();
+        tsp.three_opt_sm();
 
         let cost = tsp.calculate_path_cost();
 
@@ -371,7 +418,7 @@ mod tests {
 
         let prev_cost = tsp2.calculate_path_cost();
 
-        let _ = tsp2.three_opt_sm();
+        tsp2.three_opt_sm();
 
         let cost = tsp2.calculate_path_cost();
         println!("Cost: {cost}");
@@ -381,6 +428,98 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn candidates_wiki() -> eyre::Result<()> {
+        let WikiPaths { path_1, path_2 } = wiki_nodes()?;
+
+        for nodes in [path_1, path_2] {
+            let mut full = TSP::new(nodes.clone());
+            full.nn();
+            let nn_cost = full.calculate_path_cost();
+
+            let mut tsp = TSP::new(nodes).with_candidates(8);
+            tsp.nn();
+
+            // Candidates only change how the nearest node is found, not which one it is
+            assert_eq!(tsp.calculate_path_cost(), nn_cost);
+
+            tsp.two_opt();
+            let two_opt_cost = tsp.calculate_path_cost();
+            assert!(two_opt_cost < nn_cost);
+
+            tsp.three_opt();
+            let cost = tsp.calculate_path_cost();
+            assert!(cost <= two_opt_cost);
+            assert!(cost.floor() < 8559.0);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn strategies_wiki() -> eyre::Result<()> {
+        let WikiPaths { path_2, .. } = wiki_nodes()?;
+
+        for strategy in [Strategy::FirstImprovement, Strategy::BestImprovement] {
+            let mut tsp = TSP::new(path_2.clone())
+                .with_candidates(8)
+                .with_strategy(strategy);
+            tsp.nn();
+            let nn_cost = tsp.calculate_path_cost();
+
+            tsp.or_opt();
+            let or_opt_cost = tsp.calculate_path_cost();
+            assert!(or_opt_cost < nn_cost);
+
+            tsp.three_opt();
+            let cost = tsp.calculate_path_cost();
+            assert!(cost <= or_opt_cost);
+            assert!(cost.floor() < 8559.0);
+        }
+
+        Ok(())
+    }
+
+    // Every solver has to lower the cost it sees, so deltas have to account for direction
+    #[test]
+    fn asymmetric_wiki() -> eyre::Result<()> {
+        let WikiPaths { path_2, .. } = wiki_nodes()?;
+        let hills: Vec<Hill> = path_2
+            .iter()
+            .map(|point| Hill {
+                x: point.x,
+                y: point.y,
+            })
+            .collect();
+
+        for candidates in [None, Some(8)] {
+            let mut tsp = match candidates {
+                Some(k) => TSP::new(hills.clone()).with_candidates(k),
+                None => TSP::new(hills.clone()),
+            };
+            tsp.nn();
+            let mut cost = tsp.calculate_path_cost();
+
+            let solvers: [fn(&mut TSP<Hill>); 4] =
+                [TSP::two_opt, TSP::or_opt, TSP::three_opt, TSP::three_opt_sm];
+            for solve in solvers {
+                solve(&mut tsp);
+
+                let next = tsp.calculate_path_cost();
+                assert!(
+                    next <= cost + TOLERANCE,
+                    "{:?} {} {}",
+                    candidates,
+                    cost,
+                    next
+                );
+                cost = next;
+            }
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn swap_edges() -> eyre::Result<()> {
         let path = vec![
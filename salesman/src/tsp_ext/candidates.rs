@@ -0,0 +1,43 @@
+use crate::{
+    edge::{Edge, Spatial},
+    kdtree::KdTree,
+};
+
+// Candidate lists: for every node, the indices of its k nearest neighbours, closest first.
+// Restricting the solvers to these makes every pass close to linear in the number of nodes,
+// since good tours almost only use edges between nodes that are close to each other.
+#[derive(Debug, Clone)]
+pub struct Candidates {
+    lists: Vec<Vec<usize>>,
+}
+
+impl Candidates {
+    pub fn new<E: Edge + Spatial>(nodes: &[E], k: usize) -> Self {
+        let tree = KdTree::new(nodes.iter().map(|node| node.position()).collect());
+
+        let lists = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                // Ask for one extra since the node itself is its own nearest neighbour
+                let mut list: Vec<usize> = tree
+                    .nearest(node.position(), k + 1)
+                    .into_iter()
+                    .filter(|j| *j != i)
+                    .take(k)
+                    .collect();
+
+                // The plane only picks the candidates, the order follows the actual edge weights
+                list.sort_by(|a, b| node.weight(&nodes[*a]).total_cmp(&node.weight(&nodes[*b])));
+
+                list
+            })
+            .collect();
+
+        Self { lists }
+    }
+
+    pub fn of(&self, node: usize) -> &[usize] {
+        &self.lists[node]
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+
+use crate::{edge::Cost, tour::Tour};
+
+// Which improving move a local search applies for the node it is looking at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strategy {
+    // Apply the first improving move found, cheap per step and usually good enough
+    #[default]
+    FirstImprovement,
+    // Try every move for the node and apply the one with the largest gain
+    BestImprovement,
+}
+
+// Queue of nodes the local searches still have to look at, with a don't-look bit per node.
+// A node is only looked at again once a move has changed one of the edges next to it,
+// and those nodes go to the front of the queue so the search stays around the last change.
+#[derive(Debug, Clone)]
+pub struct DontLookBits {
+    queue: VecDeque<usize>,
+    queued: Vec<bool>,
+}
+
+impl DontLookBits {
+    // Starts out with every node queued in tour order
+    pub fn new(tour: &Tour) -> Self {
+        let queue: VecDeque<usize> = (0..tour.len()).map(|p| tour.node(p)).collect();
+
+        Self {
+            queue,
+            queued: vec![true; tour.len()],
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<usize> {
+        let node = self.queue.pop_front()?;
+        self.queued[node] = false;
+        Some(node)
+    }
+
+    pub fn wake(&mut self, node: usize) {
+        if !self.queued[node] {
+            self.queued[node] = true;
+            self.queue.push_front(node);
+        }
+    }
+}
+
+// Running sums of the edge weights along the tour in both directions. With asymmetric weights,
+// reversing a segment changes the cost of every edge inside it, and these sums give that change
+// without walking the segment.
+#[derive(Debug, Clone)]
+pub struct Reversals {
+    forward: Vec<Cost>,
+    backward: Vec<Cost>,
+}
+
+impl Reversals {
+    // `weight` is the weight between two nodes by index
+    pub fn new(tour: &Tour, weight: impl Fn(usize, usize) -> Cost) -> Self {
+        let n = tour.len();
+
+        let mut forward = vec![0.0; n];
+        let mut backward = vec![0.0; n];
+
+        for p in 1..n {
+            let (from, to) = (tour.node(p - 1), tour.node(p));
+            forward[p] = forward[p - 1] + weight(from, to);
+            backward[p] = backward[p - 1] + weight(to, from);
+        }
+
+        Self { forward, backward }
+    }
+
+    // Change in cost of the edges between positions i..=j when that segment is reversed
+    pub fn delta(&self, i: usize, j: usize) -> Cost {
+        (self.backward[j] - self.backward[i]) - (self.forward[j] - self.forward[i])
+    }
+}
@@ -1 +1,7 @@
+pub mod candidates;
+pub mod local_search;
+pub mod nn;
+pub mod or_opt;
+pub mod solver;
 pub mod three_opt;
+pub mod two_opt;
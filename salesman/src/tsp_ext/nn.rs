@@ -0,0 +1,66 @@
+use crate::{
+    edge::{Cost, Edge},
+    tour::Tour,
+    tsp_ext::solver::{Constructor, Problem},
+};
+
+// Nearest neighbour
+// Currently assumes first node is the starting point, but could start at random point.
+// With candidates the next node is the closest unvisited candidate, and we only fall back to
+// scanning every node when all candidates of the current node have been visited.
+pub struct NearestNeighbour;
+
+impl<E: Edge> Constructor<E> for NearestNeighbour {
+    fn name(&self) -> &'static str {
+        "nn"
+    }
+
+    fn construct(&self, problem: &Problem<'_, E>) -> Tour {
+        let n = problem.len();
+
+        let mut visited = vec![false; n];
+        let mut order: Vec<usize> = vec![0];
+        visited[0] = true;
+
+        let mut current_node = 0;
+
+        while order.len() < n {
+            let candidate = problem.candidates().and_then(|candidates| {
+                candidates
+                    .of(current_node)
+                    .iter()
+                    .find(|node| !visited[**node])
+                    .copied()
+            });
+
+            let chosen =
+                candidate.unwrap_or_else(|| nearest_unvisited(problem, current_node, &visited));
+
+            visited[chosen] = true;
+            order.push(chosen);
+            current_node = chosen;
+        }
+
+        Tour::new(order)
+    }
+}
+
+fn nearest_unvisited<E: Edge>(problem: &Problem<'_, E>, node: usize, visited: &[bool]) -> usize {
+    let mut current_shortest = Cost::MAX;
+    let mut current_index: usize = usize::MIN;
+
+    for (i, is_visited) in visited.iter().enumerate() {
+        if *is_visited {
+            continue;
+        }
+
+        let weight = problem.weight(node, i);
+
+        if weight < current_shortest {
+            current_shortest = weight;
+            current_index = i;
+        }
+    }
+
+    current_index
+}
@@ -0,0 +1,216 @@
+use crate::{
+    edge::{Cost, Edge, TOLERANCE},
+    tour::Tour,
+    tsp_ext::{
+        local_search::{DontLookBits, Strategy},
+        solver::{Improver, Problem},
+    },
+};
+
+// Longest segment Or-opt tries to move
+const MAX_SEGMENT: usize = 3;
+
+// NOTE: Used internally by Or-opt to keep track of which segment to move where
+#[derive(Debug, Clone, Copy)]
+struct Move {
+    start: usize,
+    len: usize,
+    after: usize,
+    reversed: bool,
+    delta: Cost,
+}
+
+// Or-opt: moves a segment of up to three nodes to somewhere else in the tour, possibly reversed.
+// https://en.wikipedia.org/wiki/Travelling_salesman_problem#Heuristic_and_approximation_algorithms
+pub struct OrOpt;
+
+impl<E: Edge> Improver<E> for OrOpt {
+    fn name(&self) -> &'static str {
+        "or_opt"
+    }
+
+    fn improve(&self, problem: &Problem<'_, E>, tour: Tour) -> Tour {
+        Search { problem, tour }.solve()
+    }
+}
+
+struct Search<'a, 'b, E> {
+    problem: &'a Problem<'b, E>,
+    tour: Tour,
+}
+
+impl<E: Edge> Search<'_, '_, E> {
+    fn dist(&self, index_1: usize, index_2: usize) -> Cost {
+        self.problem
+            .weight(self.tour.node(index_1), self.tour.node(index_2))
+    }
+
+    // Change in cost of the edges inside positions i..=j if they are reversed,
+    // only ever non-zero for asymmetric weights.
+    fn reversal(&self, i: usize, j: usize) -> Cost {
+        if E::SYMMETRIC {
+            return 0.0;
+        }
+
+        (i..j)
+            .map(|p| self.dist(p + 1, p) - self.dist(p, p + 1))
+            .sum()
+    }
+
+    fn solve(mut self) -> Tour {
+        let n = self.tour.len();
+        if n < MAX_SEGMENT + 3 {
+            return self.tour;
+        }
+
+        let mut dont_look = DontLookBits::new(&self.tour);
+
+        while let Some(node) = dont_look.pop() {
+            if let Some(found) = self.find_move(node) {
+                let end = found.start + found.len - 1;
+                for p in [
+                    self.tour.prev(found.start),
+                    found.start,
+                    end,
+                    self.tour.next(end),
+                    found.after,
+                    self.tour.next(found.after),
+                ] {
+                    dont_look.wake(self.tour.node(p));
+                }
+
+                self.tour
+                    .move_segment(found.start, found.len, found.after, found.reversed);
+            }
+        }
+
+        self.tour
+    }
+
+    // Looks at the segments starting or ending at `node`
+    fn find_move(&self, node: usize) -> Option<Move> {
+        let n = self.tour.len();
+        let p = self.tour.position(node);
+
+        let mut best: Option<Move> = None;
+
+        for len in 1..=MAX_SEGMENT {
+            for start in [Some(p), p.checked_sub(len - 1)].into_iter().flatten() {
+                let end = start + len - 1;
+                if end >= n {
+                    continue;
+                }
+
+                for after in self.insertion_points(start, end) {
+                    let Some(found) = self.evaluate(start, len, after) else {
+                        continue;
+                    };
+
+                    if best.is_none_or(|best| found.delta < best.delta) {
+                        best = Some(found);
+
+                        if self.problem.strategy() == Strategy::FirstImprovement {
+                            return best;
+                        }
+                    }
+                }
+            }
+        }
+
+        best
+    }
+
+    // Positions the segment could be inserted after. With candidates these are the positions
+    // right before and after the candidates of the segment ends, otherwise every position.
+    fn insertion_points(&self, start: usize, end: usize) -> Vec<usize> {
+        let n = self.tour.len();
+        let before = self.tour.prev(start);
+
+        let points: Vec<usize> = match self.problem.candidates() {
+            Some(candidates) => [start, end]
+                .iter()
+                .flat_map(|p| candidates.of(self.tour.node(*p)))
+                .flat_map(|node| {
+                    let q = self.tour.position(*node);
+                    [q, self.tour.prev(q)]
+                })
+                .collect(),
+            None => (0..n).collect(),
+        };
+
+        points
+            .into_iter()
+            .filter(|after| *after != before && !(start..=end).contains(after))
+            .collect()
+    }
+
+    // Best of inserting the segment as it is or reversed, if that improves the tour
+    fn evaluate(&self, start: usize, len: usize, after: usize) -> Option<Move> {
+        let end = start + len - 1;
+        let before = self.tour.prev(start);
+        let next = self.tour.next(end);
+        let after_next = self.tour.next(after);
+
+        let removed =
+            self.dist(before, start) + self.dist(end, next) + self.dist(after, after_next);
+        let common = self.dist(before, next) - removed;
+
+        let forward = common + self.dist(after, start) + self.dist(end, after_next);
+        let reversed = common
+            + self.dist(after, end)
+            + self.dist(start, after_next)
+            + self.reversal(start, end);
+
+        let (delta, reversed) = if reversed < forward {
+            (reversed, true)
+        } else {
+            (forward, false)
+        };
+
+        if delta < -TOLERANCE {
+            Some(Move {
+                start,
+                len,
+                after,
+                reversed,
+                delta,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::DistanceMatrix;
+
+    #[derive(Clone, Debug)]
+    struct OneDPoint {
+        x: f64,
+    }
+
+    impl Edge for OneDPoint {
+        fn weight(&self, node: &Self) -> Cost {
+            (self.x - node.x).abs()
+        }
+    }
+
+    // Points on a line visited in order, except for a segment that has been moved far away
+    #[test]
+    fn moves_segment_back() {
+        let nodes: Vec<OneDPoint> = (0..10).map(|x| OneDPoint { x: x as f64 }).collect();
+        let matrix = DistanceMatrix::dense(&nodes);
+
+        for strategy in [Strategy::FirstImprovement, Strategy::BestImprovement] {
+            let problem = Problem::new(&nodes, &matrix).with_strategy(strategy);
+
+            let tour = Tour::new(vec![0, 1, 5, 6, 7, 2, 3, 4, 8, 9]);
+            assert_eq!(problem.cost(&tour), 28.0);
+
+            let tour = OrOpt.improve(&problem, tour);
+            assert_eq!(problem.cost(&tour), 18.0);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{
+    edge::{Cost, Edge},
+    matrix::DistanceMatrix,
+    tour::Tour,
+    tsp_ext::{candidates::Candidates, local_search::Strategy},
+};
+
+// NOTE: What every solver gets to work with. Solvers only see nodes by their index, the weights
+// come from the matrix, and the candidates and strategy decide how the local searches look for moves.
+pub struct Problem<'a, E> {
+    nodes: &'a [E],
+    matrix: &'a DistanceMatrix,
+    candidates: Option<&'a Candidates>,
+    strategy: Strategy,
+    // Makes randomised solvers repeatable, a random seed is picked without it
+    seed: Option<u64>,
+}
+
+impl<'a, E: Edge> Problem<'a, E> {
+    pub fn new(nodes: &'a [E], matrix: &'a DistanceMatrix) -> Self {
+        Self {
+            nodes,
+            matrix,
+            candidates: None,
+            strategy: Strategy::default(),
+            seed: None,
+        }
+    }
+
+    pub fn with_candidates(mut self, candidates: &'a Candidates) -> Self {
+        self.candidates = Some(candidates);
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn candidates(&self) -> Option<&'a Candidates> {
+        self.candidates
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    // Weight between two nodes by their index in `nodes`
+    pub fn weight(&self, node_1: usize, node_2: usize) -> Cost {
+        self.matrix.get(self.nodes, node_1, node_2)
+    }
+
+    // Cost of the closed tour, including the edge back to the first node
+    pub fn cost(&self, tour: &Tour) -> Cost {
+        (0..tour.len())
+            .map(|p| self.weight(tour.node(p), tour.node(tour.next(p))))
+            .sum()
+    }
+}
+
+// Builds a tour visiting every node from scratch.
+pub trait Constructor<E: Edge> {
+    fn name(&self) -> &'static str;
+
+    fn construct(&self, problem: &Problem<'_, E>) -> Tour;
+}
+
+// Takes a tour and returns one that costs at most as much.
+pub trait Improver<E: Edge> {
+    fn name(&self) -> &'static str;
+
+    fn improve(&self, problem: &Problem<'_, E>, tour: Tour) -> Tour;
+}
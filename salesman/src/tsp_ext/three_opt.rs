@@ -1,54 +1,54 @@
 use rand::{
     distr::{Distribution, Uniform},
-    Rng,
+    rngs::StdRng,
+    Rng, SeedableRng,
 };
 
-use crate::edge::Edge;
-use std::fmt::Debug;
+use crate::{
+    edge::{Cost, Edge, TOLERANCE},
+    tour::Tour,
+    tsp_ext::{
+        candidates::Candidates,
+        local_search::{DontLookBits, Reversals, Strategy},
+        solver::{Improver, Problem},
+    },
+};
 
 // NOTE: Used internally by three-opt to keep track of which edges to swap
 #[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
 enum ThreeOptCase {
     CategoryOne {
         i: usize,
         j: usize,
-        delta: f32,
+        delta: Cost,
     },
     CategoryTwo {
         first_swap: (usize, usize),
         second_swap: (usize, usize),
-        delta: f32,
+        delta: Cost,
     },
     CategoryThree {
         first_swap: (usize, usize),
         second_swap: (usize, usize),
         third_swap: (usize, usize),
-        delta: f32,
+        delta: Cost,
     },
 }
 
 impl ThreeOptCase {
-    fn delta(&self) -> &f32 {
+    fn delta(&self) -> &Cost {
         match self {
-            ThreeOptCase::CategoryOne { i, j, delta } => delta,
-            ThreeOptCase::CategoryTwo {
-                first_swap,
-                second_swap,
-                delta,
-            } => delta,
-            ThreeOptCase::CategoryThree {
-                first_swap,
-                second_swap,
-                third_swap,
-                delta,
-            } => delta,
+            ThreeOptCase::CategoryOne { delta, .. } => delta,
+            ThreeOptCase::CategoryTwo { delta, .. } => delta,
+            ThreeOptCase::CategoryThree { delta, .. } => delta,
         }
     }
 
-    fn category_one(i: usize, j: usize, delta: f32) -> Self {
+    fn category_one(i: usize, j: usize, delta: Cost) -> Self {
         Self::CategoryOne { i, j, delta }
     }
-    fn category_two(first_swap: (usize, usize), second_swap: (usize, usize), delta: f32) -> Self {
+    fn category_two(first_swap: (usize, usize), second_swap: (usize, usize), delta: Cost) -> Self {
         Self::CategoryTwo {
             first_swap,
             second_swap,
@@ -59,7 +59,7 @@ impl ThreeOptCase {
         first_swap: (usize, usize),
         second_swap: (usize, usize),
         third_swap: (usize, usize),
-        delta: f32,
+        delta: Cost,
     ) -> Self {
         Self::CategoryThree {
             first_swap,
@@ -70,92 +70,100 @@ impl ThreeOptCase {
     }
 }
 
-pub struct ThreeOpt<E> {
-    nodes: Vec<E>,
-    path: Vec<E>,
-    i: usize,
-    j: usize,
-    k: usize,
-}
+// Choose any three edges and reconnect them in all the ways there are to combine them that do not produce cycles.
+pub struct ThreeOpt;
 
-impl<E> ThreeOpt<E>
-where
-    E: Edge + Clone + Debug,
-{
-    pub fn new(nodes: Vec<E>, path: Vec<E>) -> Self {
-        Self {
-            nodes,
-            path,
-            i: 0,
-            j: 0,
-            k: 0,
-        }
+impl<E: Edge> Improver<E> for ThreeOpt {
+    fn name(&self) -> &'static str {
+        "three_opt"
     }
 
-    pub fn path(&self) -> &Vec<E> {
-        &self.path
+    fn improve(&self, problem: &Problem<'_, E>, tour: Tour) -> Tour {
+        Search::new(problem, tour).solve()
     }
+}
 
-    pub fn calculate_path_cost(&self) -> f32 {
-        let path = &self.path;
-        let n = path.len();
-        let mut cost = self.dist(path.len() - 1, 0);
-
-        for i in 0..n - 1 {
-            let dist = self.dist(i, i + 1);
-            cost += dist;
-        }
+// Three-opt moves picked at random and accepted by simulated annealing, finished with a regular
+// three-opt descent from the best tour it has seen.
+pub struct SimulatedAnnealing;
 
-        cost
+impl<E: Edge> Improver<E> for SimulatedAnnealing {
+    fn name(&self) -> &'static str {
+        "three_opt_sm"
     }
 
-    fn dist(&self, index_1: usize, index_2: usize) -> f32 {
-        let path = &self.path;
-        path[index_1].weight(&path[index_2])
+    fn improve(&self, problem: &Problem<'_, E>, tour: Tour) -> Tour {
+        Search::new(problem, tour).solve_sm()
     }
+}
 
-    // Replaces edges path[i]->path[i+1] and path[j]->path[j+1]
-    // with path[i]->path[j] and path[i+1]->path[j+1]
-    fn swap_edges(&mut self, i: usize, j: usize) {
-        let mut i = i;
-        let mut j = j;
+struct Search<'a, 'b, E> {
+    problem: &'a Problem<'b, E>,
+    tour: Tour,
+    // Only kept up to date for asymmetric weights
+    reversals: Option<Reversals>,
+}
+
+impl<'a, 'b, E> Search<'a, 'b, E>
+where
+    E: Edge,
+{
+    fn new(problem: &'a Problem<'b, E>, tour: Tour) -> Self {
+        let mut search = Self {
+            problem,
+            tour,
+            reversals: None,
+        };
+        search.update_reversals();
+        search
+    }
 
-        i += 1;
+    fn dist(&self, index_1: usize, index_2: usize) -> Cost {
+        self.problem
+            .weight(self.tour.node(index_1), self.tour.node(index_2))
+    }
 
-        while i < j {
-            let tmp = self.path[i].clone();
-            self.path[i] = self.path[j].clone();
-            self.path[j] = tmp;
+    fn update_reversals(&mut self) {
+        if !E::SYMMETRIC {
+            let weight = |i, j| self.problem.weight(i, j);
+            self.reversals = Some(Reversals::new(&self.tour, weight));
+        }
+    }
 
-            i += 1;
-            j -= 1;
+    // Change in cost of the edges inside positions i..=j if they are reversed
+    fn reversal(&self, i: usize, j: usize) -> Cost {
+        match &self.reversals {
+            Some(reversals) => reversals.delta(i, j),
+            None => 0.0,
         }
     }
 
     fn swap_edges_by_case(&mut self, case: &ThreeOptCase) {
         match case {
-            ThreeOptCase::CategoryOne { i, j, delta } => {
-                self.swap_edges(*i, *j);
+            ThreeOptCase::CategoryOne { i, j, .. } => {
+                self.tour.swap_edges(*i, *j);
             }
             ThreeOptCase::CategoryTwo {
                 first_swap,
                 second_swap,
-                delta,
+                ..
             } => {
-                self.swap_edges(first_swap.0, first_swap.1);
-                self.swap_edges(second_swap.0, second_swap.1);
+                self.tour.swap_edges(first_swap.0, first_swap.1);
+                self.tour.swap_edges(second_swap.0, second_swap.1);
             }
             ThreeOptCase::CategoryThree {
                 first_swap,
                 second_swap,
                 third_swap,
-                delta,
+                ..
             } => {
-                self.swap_edges(first_swap.0, first_swap.1);
-                self.swap_edges(second_swap.0, second_swap.1);
-                self.swap_edges(third_swap.0, third_swap.1);
+                self.tour.swap_edges(first_swap.0, first_swap.1);
+                self.tour.swap_edges(second_swap.0, second_swap.1);
+                self.tour.swap_edges(third_swap.0, third_swap.1);
             }
         }
+
+        self.update_reversals();
     }
 
     fn get_best_case(
@@ -173,53 +181,74 @@ where
         let delta_case_1 = ThreeOptCase::category_one(
             c,
             e,
-            -self.dist(c, d) - self.dist(e, f) + self.dist(c, e) + self.dist(d, f),
+            -self.dist(c, d) - self.dist(e, f)
+                + self.dist(c, e)
+                + self.dist(d, f)
+                + self.reversal(d, e),
         );
 
         // fixed: c-d
         let delta_case_2 = ThreeOptCase::category_one(
             a,
             e,
-            -self.dist(a, b) - self.dist(e, f) + self.dist(a, e) + self.dist(b, f),
+            -self.dist(a, b) - self.dist(e, f)
+                + self.dist(a, e)
+                + self.dist(b, f)
+                + self.reversal(b, e),
         );
 
         // fixed: e-f
         let delta_case_3 = ThreeOptCase::category_one(
             a,
             c,
-            -self.dist(a, b) - self.dist(c, d) + self.dist(a, c) + self.dist(b, d),
+            -self.dist(a, b) - self.dist(c, d)
+                + self.dist(a, c)
+                + self.dist(b, d)
+                + self.reversal(b, c),
         );
 
         // Three-opt cases
 
         let common = -self.dist(a, b) - self.dist(c, d) - self.dist(e, f);
 
-        let delta_case_4 = ThreeOptCase::category_two(
-            (a, d),
-            (b, e),
-            common + self.dist(a, d) + self.dist(e, b) + self.dist(f, c),
+        // Length of the segment d..e, used to find where the reversed segments end up
+        let shift = e - c;
+
+        // a->d..e->b..c->f: both segments reversed and then the whole range, keeping their direction
+        let delta_case_4 = ThreeOptCase::category_three(
+            (a, c),
+            (c, e),
+            (a, e),
+            common + self.dist(a, d) + self.dist(e, b) + self.dist(c, f),
         );
 
+        // a->e..d->b..c->f: whole range reversed, then b..c turned back
         let delta_case_5 = ThreeOptCase::category_two(
             (a, e),
-            (c, e),
-            common + self.dist(a, e) + self.dist(d, b) + self.dist(c, f),
+            (a + shift, e),
+            common + self.dist(a, e) + self.dist(d, b) + self.dist(c, f) + self.reversal(d, e),
         );
 
+        // a->c..b->e..d->f: both segments reversed
         let delta_case_6 = ThreeOptCase::category_two(
             (a, c),
             (c, e),
-            common + self.dist(a, c) + self.dist(b, e) + self.dist(d, f),
+            common
+                + self.dist(a, c)
+                + self.dist(b, e)
+                + self.dist(d, f)
+                + self.reversal(b, c)
+                + self.reversal(d, e),
         );
 
-        let delta_case_7 = ThreeOptCase::category_three(
-            (a, d),
-            (b, e),
-            (c, e),
-            common + self.dist(a, d) + self.dist(e, c) + self.dist(b, f),
+        // a->d..e->c..b->f: whole range reversed, then d..e turned back
+        let delta_case_7 = ThreeOptCase::category_two(
+            (a, e),
+            (a, a + shift),
+            common + self.dist(a, d) + self.dist(e, c) + self.dist(b, f) + self.reversal(b, c),
         );
 
-        let cases = vec![
+        let cases = [
             delta_case_1,
             delta_case_2,
             delta_case_3,
@@ -240,44 +269,95 @@ where
         current_best.clone()
     }
 
-    // Choose any three edges and reconnect them in all the ways there are to combine them that do not produce cycles.
-    pub fn solve(&mut self) -> Vec<E> {
-        let n = self.path().len();
+    // Nodes are taken from a don't-look queue, and only triples with an edge next to that node are tried.
+    fn solve(mut self) -> Tour {
+        if self.tour.len() < 6 {
+            return self.tour;
+        }
 
-        let mut cost = self.calculate_path_cost();
+        let mut dont_look = DontLookBits::new(&self.tour);
 
-        let mut found_improvement = true;
-        while found_improvement {
-            found_improvement = false;
+        while let Some(node) = dont_look.pop() {
+            if let Some((triple, best_case)) = self.find_move(node) {
+                for p in triple {
+                    dont_look.wake(self.tour.node(p));
+                    dont_look.wake(self.tour.node(self.tour.next(p)));
+                }
 
-            'outer: for i in 0..(n - 1) {
-                let a = i;
-                let b = (i + 1) % n;
-                for j in (i + 2)..n {
-                    let c = j;
-                    let d = (j + 1) % n;
+                self.swap_edges_by_case(&best_case);
+            }
+        }
 
-                    for k in (j + 2)..n {
-                        let e = k;
-                        let f = (k + 1) % n;
+        self.tour
+    }
 
-                        let best_case = self.get_best_case(a, b, c, d, e, f);
+    // Tries the triples where the first edge is one of the two edges next to `node`.
+    // With candidates, the other two edges have to touch a candidate of the first edge's ends.
+    fn find_move(&self, node: usize) -> Option<([usize; 3], ThreeOptCase)> {
+        let n = self.tour.len();
+        let p = self.tour.position(node);
 
-                        // Only accept the best case if it improves the path cost
-                        if best_case.delta() < &-0.001 {
-                            found_improvement = true;
+        let mut best: Option<([usize; 3], ThreeOptCase)> = None;
 
-                            self.swap_edges_by_case(&best_case);
-                            cost += best_case.delta();
+        for i in [p, self.tour.prev(p)] {
+            for (j, k) in self.edge_pairs(i) {
+                let mut triple = [i, j, k];
+                triple.sort();
+                let [a, c, e] = triple;
 
-                            break 'outer;
-                        }
+                if c < a + 2 || e < c + 2 {
+                    continue;
+                }
+
+                let best_case = self.get_best_case(a, a + 1, c, c + 1, e, (e + 1) % n);
+
+                // Only accept the best case if it improves the path cost
+                if best_case.delta() < &-TOLERANCE
+                    && best
+                        .as_ref()
+                        .is_none_or(|(_, best)| best_case.delta() < best.delta())
+                {
+                    best = Some((triple, best_case));
+
+                    if self.problem.strategy() == Strategy::FirstImprovement {
+                        return best;
                     }
                 }
             }
         }
 
-        self.path.clone()
+        best
+    }
+
+    // Start of the other two edges to try together with the edge starting at position i
+    fn edge_pairs(&self, i: usize) -> Vec<(usize, usize)> {
+        let n = self.tour.len();
+
+        match self.problem.candidates() {
+            Some(candidates) => {
+                let js = self.candidate_edges(candidates, i);
+                let ks = self.candidate_edges(candidates, self.tour.next(i));
+
+                js.iter()
+                    .flat_map(|j| ks.iter().map(|k| (*j, *k)))
+                    .collect()
+            }
+            None => (0..n)
+                .flat_map(|j| ((j + 2)..n).map(move |k| (j, k)))
+                .collect(),
+        }
+    }
+
+    // Start of the edges touching a candidate of the node at path[i]
+    fn candidate_edges(&self, candidates: &Candidates, i: usize) -> Vec<usize> {
+        candidates
+            .of(self.tour.node(i))
+            .iter()
+            .flat_map(|node| {
+                let p = self.tour.position(*node);
+                [p, self.tour.prev(p)]
+            })
+            .collect()
     }
 
     // Three-opt based simulated annealing
@@ -285,67 +365,151 @@ where
     // https://optimization.cbe.cornell.edu/index.php?title=Simulated_annealing
     // file:///Users/thusanarul/Documents/three-opt-simulated-annealing.pdf
     // https://algorithmafternoon.com/books/simulated_annealing/chapter01/
-    pub fn solve_sm(&mut self) -> Vec<E> {
-        // Cool down rate constant
-        let alpha = 0.9;
+    fn solve_sm(mut self) -> Tour {
+        // Cool down rate constant, applied after every sampled triple
+        let alpha = 0.999;
 
-        let n = self.path().len();
+        let n = self.tour.len();
+        if n < 6 {
+            return self.solve();
+        }
 
         // Random sampling
-        let between = Uniform::<f32>::try_from(0.0..=1.0).unwrap();
-        let mut rng = rand::rng();
+        let between = Uniform::<f64>::try_from(0.0..=1.0).unwrap();
+        let mut rng = match self.problem.seed() {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_rng(&mut rand::rng()),
+        };
 
-        let mut cost = self.calculate_path_cost();
+        let mut cost = self.problem.cost(&self.tour);
+        let mut best_cost = cost;
+        let mut best_tour = self.tour.clone();
 
-        let mut temperature: f32 = 100000.0;
+        let mut temperature: f64 = 100000.0;
         while temperature.trunc() > 0.0 {
-            'outer: for i in 0..(n - 1) {
-                let a = i;
-                let b = (i + 1) % n;
-                for j in (i + 2)..n {
-                    let c = j;
-                    let d = (j + 1) % n;
-
-                    for k in (j + 2)..n {
-                        let e = k;
-                        let f = (k + 1) % n;
-
-                        let best_case = self.get_best_case(a, b, c, d, e, f);
-
-                        // println!("Best case: {:#?}", best_case);
-
-                        let delta = best_case.delta();
-                        // NOTE: For simulated annealing
-                        let current_temperature = temperature;
-                        temperature = alpha * current_temperature;
-
-                        let rand = between.sample(&mut rng);
-                        let prob = (-delta / current_temperature).exp();
-
-                        // We will then select the best case if:
-                        // - better than the current solution
-                        // - - Which in our case will be true if delta is negative
-                        // - or if it passes a certain probability according to the SA approach
-
-                        if delta < &-0.001 {
-                            println!("Chose improving solution: {}", delta);
-                            self.swap_edges_by_case(&best_case);
-                            cost += best_case.delta();
-                            break 'outer;
-                        } else if rand <= prob {
-                            // println!(
-                            //     "Chose worse solution: {} {} {} {}",
-                            //     delta, temperature, rand, prob
-                            // );
-                            self.swap_edges_by_case(&best_case);
-                            cost += best_case.delta();
-                            break 'outer;
-                        }
-                    }
+            // Edge n - 1 is the one closing the tour, back to the first node
+            let mut triple = [0; 3];
+            for edge in triple.iter_mut() {
+                *edge = rng.random_range(0..n);
+            }
+            triple.sort();
+            let [a, c, e] = triple;
+
+            if c < a + 2 || e < c + 2 {
+                continue;
+            }
+
+            let best_case = self.get_best_case(a, a + 1, c, c + 1, e, (e + 1) % n);
+
+            let delta = best_case.delta();
+            // NOTE: For simulated annealing
+            let current_temperature = temperature;
+            temperature = alpha * current_temperature;
+
+            let rand = between.sample(&mut rng);
+            let prob = (-delta / current_temperature).exp();
+
+            // We will then select the best case if:
+            // - better than the current solution
+            // - - Which in our case will be true if delta is negative
+            // - or if it passes a certain probability according to the SA approach
+            if delta < &-TOLERANCE || rand <= prob {
+                self.swap_edges_by_case(&best_case);
+                cost += best_case.delta();
+
+                if cost < best_cost {
+                    best_cost = cost;
+                    best_tour = self.tour.clone();
                 }
             }
         }
 
-        self.path.clone()
+        // Accepting worse solutions means we can end up above the best one we have seen,
+        // so continue from the best one and finish with a regular descent.
+        self.tour = best_tour;
+        self.update_reversals();
+
+        self.solve()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::DistanceMatrix;
+
+    #[derive(Clone, Debug)]
+    struct Point {
+        x: f64,
+        y: f64,
+    }
+
+    impl Edge for Point {
+        fn weight(&self, node: &Self) -> Cost {
+            ((self.x - node.x).powi(2) + (self.y - node.y).powi(2)).sqrt()
+        }
+    }
+
+    // Climbing costs three times as much as walking on the flat or downhill
+    #[derive(Clone, Debug)]
+    struct Hill {
+        x: f64,
+        y: f64,
+    }
+
+    impl Edge for Hill {
+        const SYMMETRIC: bool = false;
+
+        fn weight(&self, node: &Self) -> Cost {
+            let flat = ((self.x - node.x).powi(2) + (self.y - node.y).powi(2)).sqrt();
+            flat + 2.0 * (node.y - self.y).max(0.0)
+        }
+    }
+
+    fn coordinates() -> Vec<(f64, f64)> {
+        (0..12)
+            .map(|i| {
+                let i = i as f64;
+                ((i * 37.0) % 101.0, (i * 59.0) % 97.0)
+            })
+            .collect()
+    }
+
+    // The swaps of the best case should change the path cost by exactly its delta
+    #[test]
+    fn swaps_match_delta() {
+        let nodes: Vec<Point> = coordinates()
+            .into_iter()
+            .map(|(x, y)| Point { x, y })
+            .collect();
+        assert_swaps_match_delta(&nodes);
+
+        let hills: Vec<Hill> = coordinates()
+            .into_iter()
+            .map(|(x, y)| Hill { x, y })
+            .collect();
+        assert_swaps_match_delta(&hills);
+    }
+
+    fn assert_swaps_match_delta<E: Edge>(nodes: &[E]) {
+        let n = nodes.len();
+        let matrix = DistanceMatrix::dense(nodes);
+        let problem = Problem::new(nodes, &matrix);
+
+        for a in 0..(n - 1) {
+            for c in (a + 2)..n {
+                for e in (c + 2)..n {
+                    let mut search = Search::new(&problem, Tour::new((0..n).collect()));
+                    let before = problem.cost(&search.tour);
+
+                    let best_case = search.get_best_case(a, a + 1, c, c + 1, e, (e + 1) % n);
+                    search.swap_edges_by_case(&best_case);
+
+                    let after = problem.cost(&search.tour);
+
+                    assert!((after - before - best_case.delta()).abs() < TOLERANCE);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,116 @@
+use crate::{
+    edge::{Cost, Edge, TOLERANCE},
+    tour::Tour,
+    tsp_ext::{
+        local_search::{DontLookBits, Reversals, Strategy},
+        solver::{Improver, Problem},
+    },
+};
+
+// NOTE: Needs a path to optimize
+// Nodes are taken from a don't-look queue, and the two edges next to the node are tried
+// against the edges next to its candidates (or every other node without candidates).
+pub struct TwoOpt;
+
+impl<E: Edge> Improver<E> for TwoOpt {
+    fn name(&self) -> &'static str {
+        "two_opt"
+    }
+
+    fn improve(&self, problem: &Problem<'_, E>, tour: Tour) -> Tour {
+        let mut search = Search { problem, tour };
+
+        let mut dont_look = DontLookBits::new(&search.tour);
+        let mut reversals = search.reversals();
+
+        while let Some(node) = dont_look.pop() {
+            if let Some((a, c)) = search.find_move(node, reversals.as_ref()) {
+                for p in [a, a + 1, c, search.tour.next(c)] {
+                    dont_look.wake(search.tour.node(p));
+                }
+
+                search.tour.swap_edges(a, c);
+                reversals = search.reversals();
+            }
+        }
+
+        search.tour
+    }
+}
+
+struct Search<'a, 'b, E> {
+    problem: &'a Problem<'b, E>,
+    tour: Tour,
+}
+
+impl<E: Edge> Search<'_, '_, E> {
+    fn dist(&self, index_1: usize, index_2: usize) -> Cost {
+        self.problem
+            .weight(self.tour.node(index_1), self.tour.node(index_2))
+    }
+
+    // Only needed when weights are asymmetric, since two-opt reverses the segment between the edges
+    fn reversals(&self) -> Option<Reversals> {
+        if E::SYMMETRIC {
+            return None;
+        }
+
+        Some(Reversals::new(&self.tour, |i, j| self.problem.weight(i, j)))
+    }
+
+    fn find_move(&self, node: usize, reversals: Option<&Reversals>) -> Option<(usize, usize)> {
+        let n = self.tour.len();
+        let i = self.tour.position(node);
+
+        let candidates = self.problem.candidates();
+        let others: Vec<usize> = match candidates {
+            Some(candidates) => candidates.of(node).to_vec(),
+            None => (0..n).filter(|other| *other != node).collect(),
+        };
+
+        // Candidates are sorted by weight, so we can stop looking as soon as the new edge is
+        // at least as long as both edges it could replace. With asymmetric weights the reversed
+        // segment can make up for a longer edge, so then every candidate is tried.
+        let longest = self
+            .dist(i, self.tour.next(i))
+            .max(self.dist(self.tour.prev(i), i));
+
+        let mut best: Option<(usize, usize, Cost)> = None;
+
+        for other in others {
+            if E::SYMMETRIC && candidates.is_some() && self.problem.weight(node, other) >= longest {
+                break;
+            }
+
+            let j = self.tour.position(other);
+
+            // Either the edges after both nodes or the edges before both nodes are replaced
+            for (x, y) in [(i, j), (self.tour.prev(i), self.tour.prev(j))] {
+                let (a, c) = if x < y { (x, y) } else { (y, x) };
+                if c < a + 2 {
+                    continue;
+                }
+
+                let b = a + 1;
+                let d = self.tour.next(c);
+
+                // Calculate delta change if connections are switched
+                let reversal = reversals.map_or(0.0, |reversals| reversals.delta(b, c));
+                let length_delta = -self.dist(a, b) - self.dist(c, d)
+                    + self.dist(a, c)
+                    + self.dist(b, d)
+                    + reversal;
+
+                if length_delta < -TOLERANCE && best.is_none_or(|best| length_delta < best.2) {
+                    best = Some((a, c, length_delta));
+
+                    if self.problem.strategy() == Strategy::FirstImprovement {
+                        return Some((a, c));
+                    }
+                }
+            }
+        }
+
+        best.map(|(a, c, _)| (a, c))
+    }
+}
@@ -0,0 +1,563 @@
+use std::{
+    fmt::{self, Debug, Write as _},
+    fs,
+    path::Path,
+    rc::Rc,
+};
+
+use eyre::eyre;
+
+use crate::{
+    edge::{Cost, Edge, Spatial},
+    latlon::LatLon,
+    matrix::DistanceMatrix,
+    tour,
+};
+
+// Earth radius and value of pi the TSPLIB distance functions for GEO instances are defined with
+const GEO_RADIUS: f64 = 6378.388;
+#[allow(clippy::approx_constant)]
+const GEO_PI: f64 = 3.141592;
+
+// NOTE: How the weight between two nodes of an instance is calculated. Every weight is rounded to
+// a whole number the way TSPLIB defines it, so that tour costs can be compared to published optima.
+#[derive(Debug)]
+enum Weights {
+    Euc2d,
+    Ceil2d,
+    // Pseudo-Euclidean distance of the att48 and att532 instances
+    Att,
+    // Coordinates are latitude and longitude as DDD.MM, degrees and minutes
+    Geo,
+    // Full matrix, row-major
+    Explicit(Vec<Cost>),
+}
+
+// A node of a TSPLIB instance. Nodes share how their weights are calculated with every other
+// node of the same instance.
+#[derive(Clone)]
+pub struct Node {
+    // 1-based, as in the file
+    id: usize,
+    // Coordinates, or display data for explicit weights. Nodes without either sit at the origin,
+    // which leaves the solvers with arbitrary candidates.
+    x: f64,
+    y: f64,
+    weights: Rc<Weights>,
+}
+
+impl Node {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl Debug for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}, {})", self.id, self.x, self.y)
+    }
+}
+
+impl Edge for Node {
+    fn weight(&self, node: &Self) -> Cost {
+        if self.id == node.id {
+            return 0.0;
+        }
+
+        let dx = self.x - node.x;
+        let dy = self.y - node.y;
+
+        match self.weights.as_ref() {
+            Weights::Euc2d => nint((dx * dx + dy * dy).sqrt()),
+            Weights::Ceil2d => (dx * dx + dy * dy).sqrt().ceil(),
+            Weights::Att => {
+                let r = ((dx * dx + dy * dy) / 10.0).sqrt();
+                let t = nint(r);
+                if t < r {
+                    t + 1.0
+                } else {
+                    t
+                }
+            }
+            Weights::Geo => {
+                let (lat_1, lon_1) = (geo(self.x), geo(self.y));
+                let (lat_2, lon_2) = (geo(node.x), geo(node.y));
+
+                let q1 = (lon_1 - lon_2).cos();
+                let q2 = (lat_1 - lat_2).cos();
+                let q3 = (lat_1 + lat_2).cos();
+
+                (GEO_RADIUS * (0.5 * ((1.0 + q1) * q2 - (1.0 - q1) * q3)).acos() + 1.0).trunc()
+            }
+            Weights::Explicit(matrix) => {
+                let size = (matrix.len() as f64).sqrt() as usize;
+                matrix[(self.id - 1) * size + node.id - 1]
+            }
+        }
+    }
+}
+
+impl Spatial for Node {
+    fn position(&self) -> [f64; 2] {
+        match self.weights.as_ref() {
+            Weights::Geo => LatLon::new(degrees(self.x), degrees(self.y)).position(),
+            _ => [self.x, self.y],
+        }
+    }
+}
+
+fn nint(x: f64) -> Cost {
+    (x + 0.5).trunc()
+}
+
+// DDD.MM to degrees
+fn degrees(x: f64) -> f64 {
+    let deg = x.trunc();
+    deg + 5.0 * (x - deg) / 3.0
+}
+
+// DDD.MM to radians, with the rounded pi of the TSPLIB definition
+fn geo(x: f64) -> f64 {
+    GEO_PI * degrees(x) / 180.0
+}
+
+// A symmetric TSP instance read from a TSPLIB .tsp file
+#[derive(Debug)]
+pub struct Instance {
+    name: String,
+    nodes: Vec<Node>,
+}
+
+impl Instance {
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    fn parse(text: &str) -> eyre::Result<Self> {
+        let file = File::parse(text)?;
+
+        let kind = file.header("TYPE").unwrap_or("TSP");
+        if kind != "TSP" {
+            return Err(eyre!(
+                "Only symmetric TSP instances can be read, got type {}",
+                kind
+            ));
+        }
+
+        let dimension: usize = file
+            .header("DIMENSION")
+            .ok_or_else(|| eyre!("Missing DIMENSION"))?
+            .parse()?;
+        let weight_type = file
+            .header("EDGE_WEIGHT_TYPE")
+            .ok_or_else(|| eyre!("Missing EDGE_WEIGHT_TYPE"))?;
+
+        let weights = match weight_type {
+            "EUC_2D" => Weights::Euc2d,
+            "CEIL_2D" => Weights::Ceil2d,
+            "ATT" => Weights::Att,
+            "GEO" => Weights::Geo,
+            "EXPLICIT" => Weights::Explicit(explicit(
+                file.header("EDGE_WEIGHT_FORMAT").unwrap_or("FULL_MATRIX"),
+                file.section("EDGE_WEIGHT_SECTION"),
+                dimension,
+            )?),
+            _ => {
+                return Err(eyre!(
+                    "Unsupported EDGE_WEIGHT_TYPE {}, expected one of {}",
+                    weight_type,
+                    "EUC_2D, CEIL_2D, ATT, GEO or EXPLICIT"
+                ))
+            }
+        };
+        let weights = Rc::new(weights);
+
+        let coords = match weights.as_ref() {
+            Weights::Explicit(_) => file.section("DISPLAY_DATA_SECTION"),
+            _ => file.section("NODE_COORD_SECTION"),
+        };
+        let mut positions = vec![[0.0, 0.0]; dimension];
+        if !coords.is_empty() || !matches!(weights.as_ref(), Weights::Explicit(_)) {
+            if coords.len() != 3 * dimension {
+                return Err(eyre!(
+                    "Expected {} nodes with an id and two coordinates",
+                    dimension
+                ));
+            }
+
+            for node in coords.chunks(3) {
+                let id = node[0] as usize;
+                if id == 0 || id > dimension {
+                    return Err(eyre!("Node {} is outside 1..={}", node[0], dimension));
+                }
+                positions[id - 1] = [node[1], node[2]];
+            }
+        }
+
+        let nodes = positions
+            .into_iter()
+            .enumerate()
+            .map(|(i, [x, y])| Node {
+                id: i + 1,
+                x,
+                y,
+                weights: weights.clone(),
+            })
+            .collect();
+
+        Ok(Self {
+            name: file.header("NAME").unwrap_or_default().to_string(),
+            nodes,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+}
+
+// Expands the weights of an EXPLICIT instance to a full matrix. The column-wise formats of a
+// symmetric instance list the same numbers as the opposite row-wise ones.
+fn explicit(format: &str, numbers: &[f64], size: usize) -> eyre::Result<Vec<Cost>> {
+    let pairs: Vec<(usize, usize)> = match format {
+        "FULL_MATRIX" => (0..size)
+            .flat_map(|i| (0..size).map(move |j| (i, j)))
+            .collect(),
+        "UPPER_ROW" | "LOWER_COL" => (0..size)
+            .flat_map(|i| (i + 1..size).map(move |j| (i, j)))
+            .collect(),
+        "LOWER_ROW" | "UPPER_COL" => (0..size)
+            .flat_map(|i| (0..i).map(move |j| (i, j)))
+            .collect(),
+        "UPPER_DIAG_ROW" | "LOWER_DIAG_COL" => (0..size)
+            .flat_map(|i| (i..size).map(move |j| (i, j)))
+            .collect(),
+        "LOWER_DIAG_ROW" | "UPPER_DIAG_COL" => (0..size)
+            .flat_map(|i| (0..=i).map(move |j| (i, j)))
+            .collect(),
+        _ => return Err(eyre!("Unsupported EDGE_WEIGHT_FORMAT {}", format)),
+    };
+
+    if numbers.len() != pairs.len() {
+        return Err(eyre!(
+            "Expected {} weights for a {} of {} nodes, got {}",
+            pairs.len(),
+            format,
+            size,
+            numbers.len()
+        ));
+    }
+
+    let mut matrix = vec![0.0; size * size];
+    for ((i, j), weight) in pairs.into_iter().zip(numbers) {
+        matrix[i * size + j] = *weight;
+        matrix[j * size + i] = *weight;
+    }
+
+    Ok(matrix)
+}
+
+// Reads a .tour or .opt.tour file as the 0-based indices of the nodes in visiting order
+pub fn load_tour(path: impl AsRef<Path>) -> eyre::Result<Vec<usize>> {
+    parse_tour(&fs::read_to_string(path)?)
+}
+
+fn parse_tour(text: &str) -> eyre::Result<Vec<usize>> {
+    let file = File::parse(text)?;
+
+    let mut order = vec![];
+    for id in file.section("TOUR_SECTION") {
+        if *id == -1.0 {
+            break;
+        }
+        if *id < 1.0 {
+            return Err(eyre!("Node {} in tour is not a node id", id));
+        }
+        order.push(*id as usize - 1);
+    }
+
+    let mut seen = order.clone();
+    seen.sort();
+    seen.dedup();
+    if seen.len() != order.len() || seen.last().is_some_and(|last| *last >= order.len()) {
+        return Err(eyre!("The tour does not visit every node exactly once"));
+    }
+
+    Ok(order)
+}
+
+// Writes the nodes as an EXPLICIT instance with the weights from `matrix`, multiplied by `scale`
+// and rounded since TSPLIB weights are whole numbers. Asymmetric nodes make an ATSP instance.
+// The positions of the nodes go in the display data.
+pub fn save<E: Edge + Spatial>(
+    path: impl AsRef<Path>,
+    name: &str,
+    comment: &str,
+    nodes: &[E],
+    matrix: &DistanceMatrix,
+    scale: Cost,
+) -> eyre::Result<()> {
+    let mut text = String::new();
+
+    writeln!(text, "NAME : {}", name)?;
+    writeln!(text, "TYPE : {}", if E::SYMMETRIC { "TSP" } else { "ATSP" })?;
+    writeln!(text, "COMMENT : {}", comment)?;
+    writeln!(text, "DIMENSION : {}", nodes.len())?;
+    writeln!(text, "EDGE_WEIGHT_TYPE : EXPLICIT")?;
+    writeln!(text, "EDGE_WEIGHT_FORMAT : FULL_MATRIX")?;
+    writeln!(text, "DISPLAY_DATA_TYPE : TWOD_DISPLAY")?;
+
+    writeln!(text, "EDGE_WEIGHT_SECTION")?;
+    for i in 0..nodes.len() {
+        let row: Vec<String> = (0..nodes.len())
+            .map(|j| format!("{}", (matrix.get(nodes, i, j) * scale).round()))
+            .collect();
+        writeln!(text, "{}", row.join(" "))?;
+    }
+
+    writeln!(text, "DISPLAY_DATA_SECTION")?;
+    for (i, node) in nodes.iter().enumerate() {
+        let [x, y] = node.position();
+        writeln!(text, "{} {:.3} {:.3}", i + 1, x, y)?;
+    }
+    writeln!(text, "EOF")?;
+
+    Ok(fs::write(path, text)?)
+}
+
+// Writes a closed order of node indices, as returned by the solvers, as a .tour file
+pub fn save_tour(path: impl AsRef<Path>, name: &str, order: &[usize]) -> eyre::Result<()> {
+    // The node the tour returns to is only listed once
+    let order = tour::visits(order);
+
+    let mut text = String::new();
+    writeln!(text, "NAME : {}", name)?;
+    writeln!(text, "TYPE : TOUR")?;
+    writeln!(text, "DIMENSION : {}", order.len())?;
+    writeln!(text, "TOUR_SECTION")?;
+    for i in order {
+        writeln!(text, "{}", i + 1)?;
+    }
+    writeln!(text, "-1")?;
+    writeln!(text, "EOF")?;
+
+    Ok(fs::write(path, text)?)
+}
+
+// NOTE: The specification part of a TSPLIB file as `KEY : value` pairs, and the numbers of every
+// data section. Unknown keywords and sections are kept, only the ones asked for are interpreted.
+struct File<'a> {
+    headers: Vec<(&'a str, &'a str)>,
+    sections: Vec<(&'a str, Vec<f64>)>,
+}
+
+impl<'a> File<'a> {
+    fn parse(text: &'a str) -> eyre::Result<Self> {
+        let mut headers = vec![];
+        let mut sections: Vec<(&str, Vec<f64>)> = vec![];
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if line == "EOF" {
+                break;
+            }
+
+            if let Some((key, value)) = line.split_once(':') {
+                headers.push((key.trim(), value.trim()));
+                continue;
+            }
+
+            let first = line.split_whitespace().next().unwrap_or_default();
+            if first.ends_with("_SECTION") {
+                sections.push((first, vec![]));
+                continue;
+            }
+
+            let Some((name, numbers)) = sections.last_mut() else {
+                return Err(eyre!("Unexpected line before any section: {}", line));
+            };
+            for number in line.split_whitespace() {
+                numbers.push(
+                    number
+                        .parse()
+                        .map_err(|_| eyre!("Expected a number in {}, got {}", name, number))?,
+                );
+            }
+        }
+
+        Ok(Self { headers, sections })
+    }
+
+    fn header(&self, key: &str) -> Option<&'a str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| *value)
+    }
+
+    fn section(&self, name: &str) -> &[f64] {
+        self.sections
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, numbers)| numbers.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compare::{compare, PIPELINES},
+        pipeline::PipelineConfig,
+        tsp_ext::{candidates::Candidates, local_search::Strategy},
+    };
+
+    fn dir() -> String {
+        format!("{}/src/tests/tsplib", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn cost(nodes: &[Node], order: &[usize]) -> Cost {
+        (0..order.len())
+            .map(|p| nodes[order[p]].weight(&nodes[order[(p + 1) % order.len()]]))
+            .sum()
+    }
+
+    #[test]
+    fn reads_weight_types() -> eyre::Result<()> {
+        let att = Instance::parse(
+            "NAME: tiny\nTYPE: TSP\nDIMENSION: 2\nEDGE_WEIGHT_TYPE: ATT\nNODE_COORD_SECTION\n1 0 0\n2 30 40\nEOF\n",
+        )?;
+        // sqrt(2500 / 10) = 15.81, rounded to 16
+        assert_eq!(att.nodes()[0].weight(&att.nodes()[1]), 16.0);
+
+        // One degree of latitude apart on the TSPLIB globe
+        let geo = Instance::parse(
+            "DIMENSION: 2\nEDGE_WEIGHT_TYPE: GEO\nNODE_COORD_SECTION\n1 59.30 10.45\n2 60.30 10.45\n",
+        )?;
+        assert_eq!(geo.nodes()[0].weight(&geo.nodes()[1]), 112.0);
+
+        let full = Instance::parse(
+            "DIMENSION: 3\nEDGE_WEIGHT_TYPE: EXPLICIT\nEDGE_WEIGHT_FORMAT: FULL_MATRIX\nEDGE_WEIGHT_SECTION\n0 1 2\n1 0 3\n2 3 0\n",
+        )?;
+        let lower = Instance::parse(
+            "DIMENSION: 3\nEDGE_WEIGHT_TYPE: EXPLICIT\nEDGE_WEIGHT_FORMAT: LOWER_DIAG_ROW\nEDGE_WEIGHT_SECTION\n0 1 0 2 3 0\n",
+        )?;
+        for i in 0..3 {
+            for j in 0..3 {
+                assert_eq!(
+                    full.nodes()[i].weight(&full.nodes()[j]),
+                    lower.nodes()[i].weight(&lower.nodes()[j])
+                );
+            }
+        }
+        assert_eq!(full.nodes()[2].weight(&full.nodes()[1]), 3.0);
+
+        assert!(Instance::parse("TYPE: ATSP\nDIMENSION: 1\nEDGE_WEIGHT_TYPE: EXPLICIT\n").is_err());
+        assert!(Instance::parse("DIMENSION: 2\nEDGE_WEIGHT_TYPE: XRAY1\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn writes_what_it_reads() -> eyre::Result<()> {
+        let instance = Instance::load(format!("{}/berlin52.tsp", dir()))?;
+        let nodes = &instance.nodes()[..10];
+        let matrix = DistanceMatrix::dense(nodes);
+
+        let path = std::env::temp_dir().join("salesman-tsplib-round-trip.tsp");
+        save(
+            &path,
+            "berlin10",
+            "First ten of berlin52",
+            nodes,
+            &matrix,
+            1.0,
+        )?;
+        let written = Instance::load(&path)?;
+
+        assert_eq!(written.name(), "berlin10");
+        for (a, b) in nodes.iter().zip(written.nodes()) {
+            assert_eq!(a.position(), b.position());
+            for (c, d) in nodes.iter().zip(written.nodes()) {
+                assert_eq!(a.weight(c), b.weight(d));
+            }
+        }
+
+        let order = vec![3, 1, 0, 2, 4, 5, 6, 7, 8, 9, 3];
+        let tour_path = path.with_extension("tour");
+        save_tour(&tour_path, "berlin10", &order)?;
+        assert_eq!(load_tour(&tour_path)?, order[..10]);
+
+        fs::remove_file(path)?;
+        fs::remove_file(tour_path)?;
+
+        Ok(())
+    }
+
+    // Every default pipeline stays within `gaps` of the published optimum, from best to worst of
+    // a few seeds
+    fn regression(name: &str, optimum: Cost, gaps: [f64; 5]) -> eyre::Result<()> {
+        let instance = Instance::load(format!("{}/{}.tsp", dir(), name))?;
+        let nodes = instance.nodes();
+        let matrix = DistanceMatrix::dense(nodes);
+        let candidates = Candidates::new(nodes, 10);
+
+        let pipelines = PIPELINES
+            .iter()
+            .map(|pipeline| pipeline.parse())
+            .collect::<eyre::Result<Vec<PipelineConfig>>>()?;
+        let comparison = compare(
+            nodes,
+            &matrix,
+            &candidates,
+            Strategy::default(),
+            &pipelines,
+            &[0, 1, 2],
+        );
+
+        for (row, gap) in comparison.rows.iter().zip(gaps) {
+            assert!(
+                optimum <= row.min(),
+                "{} beat the optimum of {}",
+                row.pipeline,
+                name
+            );
+            assert!(
+                row.max() <= optimum * (1.0 + gap),
+                "{} on {} cost {}, more than {}% over {}",
+                row.pipeline,
+                name,
+                row.max(),
+                gap * 100.0,
+                optimum
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn berlin52_optimal_tour() -> eyre::Result<()> {
+        let instance = Instance::load(format!("{}/berlin52.tsp", dir()))?;
+        let optimal = load_tour(format!("{}/berlin52.opt.tour", dir()))?;
+
+        assert_eq!(optimal.len(), 52);
+        assert_eq!(cost(instance.nodes(), &optimal), 7542.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn berlin52_regression() -> eyre::Result<()> {
+        regression("berlin52", 7542.0, [0.20, 0.10, 0.10, 0.08, 0.08])
+    }
+
+    #[test]
+    fn kroa100_regression() -> eyre::Result<()> {
+        regression("kroA100", 21282.0, [0.35, 0.10, 0.10, 0.08, 0.08])
+    }
+}
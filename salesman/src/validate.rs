@@ -0,0 +1,271 @@
+use std::{collections::HashMap, fmt};
+
+use serde::Serialize;
+
+use crate::sources::Input;
+
+// South-west and north-east corners of Oslo, anything outside was most likely geocoded wrong
+const OSLO: [(f64, f64); 2] = [(59.80, 10.48), (60.14, 10.95)];
+
+// What the step filling in `where` writes when it doesn't know, compared in lowercase
+const PLACEHOLDERS: [&str; 8] = ["n/a", "na", "-", "?", "unknown", "ukjent", "none", "null"];
+
+// NOTE: Something wrong with one row of the input, rows counted from 1 in the order they were
+// read, not counting a header line. Rows that can't be read and missing fields that can't be
+// filled in reject a row, the rest are kept but probably not what was meant.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub row: usize,
+    pub title: String,
+    pub issue: Issue,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Issue {
+    Unreadable { reason: String },
+    Missing { fields: Vec<&'static str> },
+    FilledIn { fields: Vec<&'static str> },
+    OutsideOslo { lat: f64, lon: f64 },
+    DuplicateTitle { of: usize },
+    DuplicateLink { of: usize },
+    SharedCoordinates { with: usize },
+    Placeholder { r#where: String },
+}
+
+impl Issue {
+    pub fn rejects(&self) -> bool {
+        matches!(self, Issue::Unreadable { .. } | Issue::Missing { .. })
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::Unreadable { reason } => write!(f, "unreadable, {}", reason),
+            Issue::Missing { fields } => write!(f, "missing {}", fields.join(", ")),
+            Issue::FilledIn { fields } => write!(f, "missing {}, filled in", fields.join(", ")),
+            Issue::OutsideOslo { lat, lon } => {
+                write!(f, "outside Oslo at {:.5}, {:.5}", lat, lon)
+            }
+            Issue::DuplicateTitle { of } => write!(f, "same title as row {}", of),
+            Issue::DuplicateLink { of } => write!(f, "same link as row {}", of),
+            Issue::SharedCoordinates { with } => write!(f, "same coordinates as row {}", with),
+            Issue::Placeholder { r#where } => write!(f, "placeholder where {:?}", r#where),
+        }
+    }
+}
+
+// Every problem with the rows, in row order. Duplicates point back at the first row they repeat.
+pub fn validate(input: &Input) -> Vec<Finding> {
+    let mut findings = vec![];
+    let mut titles: HashMap<String, usize> = HashMap::new();
+    let mut links: HashMap<&str, usize> = HashMap::new();
+    let mut coordinates: HashMap<(u64, u64), (usize, Option<&str>)> = HashMap::new();
+
+    for (i, statue) in input.rows.iter().enumerate() {
+        let row = i + 1;
+        let statue = match statue {
+            Ok(statue) => statue,
+            Err(reason) => {
+                findings.push(Finding {
+                    row,
+                    title: String::new(),
+                    issue: Issue::Unreadable {
+                        reason: reason.clone(),
+                    },
+                });
+                continue;
+            }
+        };
+        let mut found = |issue| {
+            findings.push(Finding {
+                row,
+                title: statue.title().unwrap_or_default().to_string(),
+                issue,
+            })
+        };
+
+        let missing = statue.missing();
+        let unfilled = input.fill(statue.clone()).missing();
+        if !unfilled.is_empty() {
+            found(Issue::Missing {
+                fields: unfilled.clone(),
+            });
+        }
+        let filled: Vec<&'static str> = missing
+            .into_iter()
+            .filter(|field| !unfilled.contains(field))
+            .collect();
+        if !filled.is_empty() {
+            found(Issue::FilledIn { fields: filled });
+        }
+
+        if let Some((lat, lon)) = statue.coordinates() {
+            let [(south, west), (north, east)] = OSLO;
+            if !(south..=north).contains(&lat) || !(west..=east).contains(&lon) {
+                found(Issue::OutsideOslo { lat, lon });
+            }
+
+            // The same statue twice is already a duplicate title, this is for geocodes that
+            // fell back to the same spot for different statues
+            let key = (lat.to_bits(), lon.to_bits());
+            match coordinates.get(&key) {
+                Some((with, title)) if *title != statue.title() => {
+                    found(Issue::SharedCoordinates { with: *with })
+                }
+                Some(_) => (),
+                None => {
+                    coordinates.insert(key, (row, statue.title()));
+                }
+            }
+        }
+
+        if let Some(title) = statue.title() {
+            match titles.get(&title.trim().to_lowercase()) {
+                Some(of) => found(Issue::DuplicateTitle { of: *of }),
+                None => {
+                    titles.insert(title.trim().to_lowercase(), row);
+                }
+            }
+        }
+
+        if !statue.link().is_empty() {
+            match links.get(statue.link()) {
+                Some(of) => found(Issue::DuplicateLink { of: *of }),
+                None => {
+                    links.insert(statue.link(), row);
+                }
+            }
+        }
+
+        if PLACEHOLDERS.contains(&statue.r#where().trim().to_lowercase().as_str()) {
+            found(Issue::Placeholder {
+                r#where: statue.r#where().to_string(),
+            });
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statue::MaybeStatue;
+
+    fn statue(title: &str, r#where: &str, link: &str, latlon: Option<(f64, f64)>) -> MaybeStatue {
+        MaybeStatue::new(
+            Some(title.to_string()),
+            r#where.to_string(),
+            link.to_string(),
+            latlon.map(|(lat, _)| lat),
+            latlon.map(|(_, lon)| lon),
+            latlon.map(|_| "Karl Johans gate 1".to_string()),
+        )
+    }
+
+    #[test]
+    fn finds_every_issue() {
+        let statues = vec![
+            statue("Tigern", "Jernbanetorget", "a", Some((59.9111, 10.7503))),
+            statue("Sinnataggen", "N/A", "b", Some((59.9270, 10.7004))),
+            statue("Lost", "Somewhere", "c", None),
+            statue("tigern ", "Jernbanetorget", "d", Some((59.9112, 10.7504))),
+            statue("Bergen", "Bryggen", "a", Some((60.3971, 5.3245))),
+            statue("Geocoded", "Oslo", "e", Some((59.9270, 10.7004))),
+        ];
+
+        let input = Input {
+            rows: statues.into_iter().map(Ok).collect(),
+            fallbacks: false,
+            header: true,
+        };
+        let issues: Vec<(usize, Issue)> = validate(&input)
+            .into_iter()
+            .map(|finding| (finding.row, finding.issue))
+            .collect();
+
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    2,
+                    Issue::Placeholder {
+                        r#where: "N/A".to_string()
+                    }
+                ),
+                (
+                    3,
+                    Issue::Missing {
+                        fields: vec!["lat", "lon", "address"]
+                    }
+                ),
+                (4, Issue::DuplicateTitle { of: 1 }),
+                (
+                    5,
+                    Issue::OutsideOslo {
+                        lat: 60.3971,
+                        lon: 5.3245
+                    }
+                ),
+                (5, Issue::DuplicateLink { of: 1 }),
+                (6, Issue::SharedCoordinates { with: 2 }),
+            ]
+        );
+        assert!(issues[1].1.rejects());
+        assert!(!issues[0].1.rejects());
+    }
+
+    #[test]
+    fn reports_what_is_filled_in() {
+        let untitled = |latlon: Option<(f64, f64)>| {
+            MaybeStatue::new(
+                None,
+                String::new(),
+                String::new(),
+                latlon.map(|(lat, _)| lat),
+                latlon.map(|(_, lon)| lon),
+                None,
+            )
+        };
+        let input = Input {
+            rows: vec![
+                Ok(untitled(Some((59.9111, 10.7503)))),
+                Ok(untitled(Some((59.9270, 10.7004)))),
+                Ok(untitled(None)),
+            ],
+            fallbacks: true,
+            header: false,
+        };
+
+        let issues: Vec<(usize, Issue)> = validate(&input)
+            .into_iter()
+            .map(|finding| (finding.row, finding.issue))
+            .collect();
+
+        // Untitled sculptures are not duplicates of each other
+        let filled = || Issue::FilledIn {
+            fields: vec!["title", "address"],
+        };
+        assert_eq!(
+            issues,
+            vec![
+                (1, filled()),
+                (2, filled()),
+                (
+                    3,
+                    Issue::Missing {
+                        fields: vec!["lat", "lon", "address"]
+                    }
+                ),
+                (
+                    3,
+                    Issue::FilledIn {
+                        fields: vec!["title"]
+                    }
+                ),
+            ]
+        );
+    }
+}